@@ -0,0 +1,74 @@
+// Measure the latency of dependent loads as the working set grows, so each level of the
+// cache hierarchy shows up as a step in the latency.
+//
+// Usage: ./cachebench <max size in MB> [stride in bytes]
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/mman.h>
+#include <time.h>
+
+#define MIN_SIZE (4ul << 10)
+#define NUM_ACCESSES (1ul << 25)
+
+int main(int argc, char *argv[]) {
+	unsigned long max_size;
+	unsigned long stride = 64;
+	struct timespec start, end;
+	char *buf;
+	void **p = NULL;
+
+	if (argc < 2) {
+		printf("Usage: %s <max size in MB> [stride in bytes]\n", argv[0]);
+		return -1;
+	}
+	max_size = strtoul(argv[1], NULL, 10) << 20;
+	if (argc >= 3) {
+		stride = strtoul(argv[2], NULL, 10);
+	}
+	if (stride < sizeof(void *)) {
+		printf("The stride must be at least %lu bytes\n", sizeof(void *));
+		return -1;
+	}
+
+	buf = mmap(NULL, max_size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+	if (buf == MAP_FAILED) {
+		perror("mmap");
+		return -1;
+	}
+
+	printf("size_bytes latency_ns\n");
+	for (unsigned long size = MIN_SIZE; size <= max_size; size *= 2) {
+		unsigned long n = size / stride;
+		unsigned long *order = malloc(n * sizeof(unsigned long));
+		double elapsed;
+
+		// Link the slots into one random cycle so the prefetchers can't help
+		for (unsigned long i = 0; i < n; i++)
+			order[i] = i;
+		for (unsigned long i = n - 1; i > 0; i--) {
+			unsigned long j = rand() % (i + 1);
+			unsigned long tmp = order[i];
+			order[i] = order[j];
+			order[j] = tmp;
+		}
+		for (unsigned long i = 0; i < n; i++)
+			*(void **)(buf + order[i] * stride) = buf + order[(i + 1) % n] * stride;
+
+		p = (void **)(buf + order[0] * stride);
+		clock_gettime(CLOCK_MONOTONIC, &start);
+		for (unsigned long i = 0; i < NUM_ACCESSES; i++)
+			p = *p;
+		clock_gettime(CLOCK_MONOTONIC, &end);
+
+		elapsed = (end.tv_sec - start.tv_sec) * 1e9 + (end.tv_nsec - start.tv_nsec);
+		printf("%lu %.2f\n", size, elapsed / NUM_ACCESSES);
+		free(order);
+	}
+
+	// Keep the compiler from optimizing away the loads
+	if (p == NULL)
+		printf("unreachable\n");
+
+	munmap(buf, max_size);
+	return 0;
+}
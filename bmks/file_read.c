@@ -0,0 +1,139 @@
+#include <unistd.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <fcntl.h>
+#include <sys/mman.h>
+#include <pthread.h>
+#include <stdbool.h>
+#include <time.h>
+
+#define PAGE_SHIFT (12)
+#define PAGE_SIZE (1ul << PAGE_SHIFT)
+#define WRITE_CHUNK (1ul << 20)
+
+unsigned long size;
+unsigned long num_threads = 1;
+bool random_pattern = false;
+char *base;
+volatile int begin = 0;
+
+struct thread_args {
+	unsigned long first_page;
+	unsigned long num_pages;
+	unsigned int seed;
+	unsigned long sum;
+};
+
+void *read_thread(void *ptr) {
+	struct thread_args *args = (struct thread_args *)ptr;
+	unsigned long sum = 0;
+
+	while (!begin) {}
+
+	for (unsigned long i = 0; i < args->num_pages; i++) {
+		unsigned long page;
+
+		if (random_pattern)
+			page = args->first_page + (rand_r(&args->seed) % args->num_pages);
+		else
+			page = args->first_page + i;
+
+		sum += base[page << PAGE_SHIFT];
+	}
+
+	// Keep the compiler from optimizing away the reads
+	args->sum = sum;
+	return NULL;
+}
+
+int create_file(char *path) {
+	char *buf;
+	int fd;
+
+	fd = open(path, O_CREAT | O_TRUNC | O_RDWR, 0644);
+	if (fd < 0) {
+		perror("open");
+		return -1;
+	}
+
+	buf = malloc(WRITE_CHUNK);
+	memset(buf, 1, WRITE_CHUNK);
+	for (unsigned long written = 0; written < size; written += WRITE_CHUNK) {
+		if (write(fd, buf, WRITE_CHUNK) != WRITE_CHUNK) {
+			perror("write");
+			free(buf);
+			close(fd);
+			return -1;
+		}
+	}
+	free(buf);
+	fsync(fd);
+	close(fd);
+
+	return 0;
+}
+
+int main(int argc, char *argv[]) {
+	struct thread_args *args;
+	pthread_t *threads;
+	struct timespec start, end;
+	unsigned long pages_per_thread;
+	double elapsed;
+	int fd;
+
+	if (argc < 3) {
+		printf("Usage: %s <file> <size in GB> [threads] [seq|rand]\n", argv[0]);
+		return -1;
+	}
+	size = strtoul(argv[2], NULL, 10) << 30;
+	if (argc >= 4) {
+		num_threads = strtoul(argv[3], NULL, 10);
+	}
+	if (argc >= 5) {
+		random_pattern = strcmp(argv[4], "rand") == 0;
+	}
+
+	if (create_file(argv[1]))
+		return -1;
+	printf("Created %lu byte file\n", size);
+
+	fd = open(argv[1], O_RDONLY);
+	if (fd < 0) {
+		perror("open");
+		return -1;
+	}
+	base = mmap(NULL, size, PROT_READ, MAP_SHARED, fd, 0);
+	if (base == MAP_FAILED) {
+		perror("mmap");
+		return -1;
+	}
+
+	args = calloc(num_threads, sizeof(struct thread_args));
+	threads = malloc(num_threads * sizeof(pthread_t));
+	pages_per_thread = (size >> PAGE_SHIFT) / num_threads;
+	for (int i = 0; i < num_threads; i++) {
+		args[i].first_page = i * pages_per_thread;
+		args[i].num_pages = pages_per_thread;
+		args[i].seed = i;
+		pthread_create(&threads[i], NULL, read_thread, &args[i]);
+	}
+
+	clock_gettime(CLOCK_MONOTONIC, &start);
+	begin = 1;
+	for (int i = 0; i < num_threads; i++) {
+		pthread_join(threads[i], NULL);
+	}
+	clock_gettime(CLOCK_MONOTONIC, &end);
+
+	elapsed = (end.tv_sec - start.tv_sec) + (end.tv_nsec - start.tv_nsec) / 1e9;
+	printf("Read %lu pages in %f seconds\n", pages_per_thread * num_threads, elapsed);
+	printf("Read bandwidth: %f MB/s\n",
+		(pages_per_thread * num_threads * PAGE_SIZE) / elapsed / (1 << 20));
+
+	munmap(base, size);
+	close(fd);
+	free(args);
+	free(threads);
+	return 0;
+}
@@ -0,0 +1,13 @@
+// An LD_PRELOAD shim that locks all of the current and future pages of the process
+// into memory, so reclaim never touches the workload.
+//
+// Usage: LD_PRELOAD=./mlockall.so <workload>
+#include <stdio.h>
+#include <sys/mman.h>
+
+__attribute__((constructor))
+static void lock_all_pages(void) {
+	if (mlockall(MCL_CURRENT | MCL_FUTURE)) {
+		perror("mlockall (is RLIMIT_MEMLOCK high enough?)");
+	}
+}
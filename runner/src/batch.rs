@@ -0,0 +1,48 @@
+/// Run several experiments back to back on the same remote, sharing one reboot.
+use clap::clap_app;
+
+pub fn cli_options() -> clap::App<'static, 'static> {
+    clap_app! { batch =>
+        (about: "Run a list of `fbmm_exp` experiments one after the other, rebooting the remote \
+         only before the first one. Requires `sudo`.")
+        (@setting ArgRequiredElseHelp)
+        (@setting DisableVersion)
+        (@arg BATCH_FILE: +required +takes_value
+         "A file with the arguments of one `fbmm_exp` run per line, i.e., \
+         `HOSTNAME USERNAME [OPTIONS] <workload> ...`. Arguments are split on whitespace. Empty lines \
+         and lines starting with # are ignored. All runs must use the same remote and kernel \
+         command line options.")
+        (@arg CONTINUE_ON_ERROR: --continue_on_error
+         "Keep going when a run fails. Each failed run and its error are recorded in \
+         BATCH_FILE.failures, and the batch fails at the end if any run failed.")
+    }
+}
+
+pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
+    let batch_file = sub_m.value_of("BATCH_FILE").unwrap();
+    let batch = std::fs::read_to_string(batch_file)?;
+
+    let runs: Vec<Vec<String>> = batch
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split_whitespace().map(Into::into).collect())
+        .collect();
+
+    if runs.is_empty() {
+        return Err(failure::format_err!("{} has no runs", batch_file));
+    }
+
+    let failures_file = sub_m
+        .is_present("CONTINUE_ON_ERROR")
+        .then(|| format!("{}.failures", batch_file));
+
+    // Don't mix in the failures of a previous batch
+    if let Some(failures_file) = &failures_file {
+        if std::path::Path::new(failures_file).exists() {
+            std::fs::remove_file(failures_file)?;
+        }
+    }
+
+    crate::fbmm_exp::batch(&runs, failures_file.as_deref())
+}
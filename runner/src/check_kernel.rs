@@ -0,0 +1,55 @@
+/// Check that the kernel on a remote exposes all of the FBMM knobs the runner uses.
+use clap::clap_app;
+
+use libscail::Login;
+
+use spurs::{cmd, Execute};
+
+pub fn cli_options() -> clap::App<'static, 'static> {
+    clap_app! { check_kernel =>
+        (about: "Reboot the remote and check that its kernel has the FBMM sysfs files the \
+         runner uses. Requires `sudo`.")
+        (@setting ArgRequiredElseHelp)
+        (@setting DisableVersion)
+        (@arg HOSTNAME: +required +takes_value
+         "The domain name of the remote")
+        (@arg USERNAME: +required +takes_value
+         "The username on the remote")
+    }
+}
+
+pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
+    let login = Login {
+        username: sub_m.value_of("USERNAME").unwrap(),
+        hostname: sub_m.value_of("HOSTNAME").unwrap(),
+        host: sub_m.value_of("HOSTNAME").unwrap(),
+    };
+
+    let ushell = crate::fbmm_exp::connect_and_setup_host(
+        &login,
+        true,
+        None,
+        std::time::Duration::from_secs(0),
+        &crate::fbmm_exp::RebootLimits::default(),
+    )?;
+
+    let mut missing = Vec::new();
+    for file in crate::fbmm_exp::FBMM_SYSFS_FILES {
+        if ushell.run(cmd!("test -e {}", file)).is_ok() {
+            println!("present: {}", file);
+        } else {
+            println!("MISSING: {}", file);
+            missing.push(*file);
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(failure::format_err!(
+            "The kernel is missing {} FBMM sysfs files: {}",
+            missing.len(),
+            missing.join(", ")
+        ))
+    }
+}
@@ -7,8 +7,9 @@ use libscail::{
     set_kernel_printk_level, time, validator,
     workloads::{
         gen_perf_command_prefix, run_canneal, run_spec17, CannealWorkload, MemcachedWorkloadConfig,
-        PostgresWorkloadConfig, Spec2017Workload, TasksetCtxBuilder, TasksetCtxInterleaving,
-        YcsbConfig, YcsbDistribution, YcsbSession, YcsbSystem, YcsbWorkload,
+        PostgresWorkloadConfig, RedisWorkloadConfig, Spec2017Workload, TasksetCtxBuilder,
+        TasksetCtxInterleaving, YcsbConfig, YcsbDistribution, YcsbSession, YcsbSystem,
+        YcsbWorkload,
     },
     Login, ScailError,
 };
@@ -17,9 +18,49 @@ use serde::{Deserialize, Serialize};
 
 use spurs::{cmd, Execute, SshShell};
 use spurs_util::escape_for_bash;
+use std::io::Write;
 use std::time::Instant;
 
-pub const PERIOD: usize = 10; // seconds
+/// The remote of the current run and the commands that stop what the run started there, for
+/// when the runner is interrupted.
+static TEARDOWN: std::sync::Mutex<Option<(String, String, Vec<String>)>> =
+    std::sync::Mutex::new(None);
+
+/// The FBMM sysfs files the runner reads or writes.
+const FBMM_STATE: &str = "/sys/kernel/mm/fbmm/state";
+const FBMM_STATS: &str = "/sys/kernel/mm/fbmm/stats";
+const FBMM_PTE_FAULT_SIZE: &str = "/sys/kernel/mm/fbmm/pte_fault_size";
+const FBMM_NT_HUGE_PAGE_ZERO: &str = "/sys/kernel/mm/fbmm/nt_huge_page_zero";
+const FBMM_FOLLOW_PAGE_MASK_FIX: &str = "/sys/kernel/mm/fbmm/follow_page_mask_fix";
+const FBMM_PMEM_WRITE_ZEROES: &str = "/sys/kernel/mm/fbmm/pmem_write_zeroes";
+const FBMM_TRACK_PFN_INSERT: &str = "/sys/kernel/mm/fbmm/track_pfn_insert";
+const FBMM_MARK_INODE_DIRTY: &str = "/sys/kernel/mm/fbmm/mark_inode_dirty";
+const FBMM_PREALLOC_MAP_POPULATE: &str = "/sys/kernel/mm/fbmm/prealloc_map_populate";
+
+/// All of the FBMM sysfs files above, for `check_kernel`.
+pub(crate) const FBMM_SYSFS_FILES: &[&str] = &[
+    FBMM_STATE,
+    FBMM_STATS,
+    FBMM_PTE_FAULT_SIZE,
+    FBMM_NT_HUGE_PAGE_ZERO,
+    FBMM_FOLLOW_PAGE_MASK_FIX,
+    FBMM_PMEM_WRITE_ZEROES,
+    FBMM_TRACK_PFN_INSERT,
+    FBMM_MARK_INODE_DIRTY,
+    FBMM_PREALLOC_MAP_POPULATE,
+];
+
+/// Where the runner records the PID of a workload it launches itself, for the collectors.
+const WORKLOAD_PID_FILE: &str = "/tmp/workload_pid";
+
+/// The default period of the periodic collectors, in seconds.
+pub const PERIOD: usize = 10;
+
+/// How long to wait for an already loaded server to reach the --wait_rss_gb RSS.
+const SERVER_RSS_WAIT_SECONDS: usize = 60;
+
+/// The second of the run when gups-hotset-move moves its hot set.
+const HOTSET_MOVE_SECOND: usize = 150;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 enum PagewalkCoherenceMode {
@@ -27,7 +68,7 @@ enum PagewalkCoherenceMode {
     Coherence,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 enum Workload {
     Spec2017Mcf,
     Spec2017Xalancbmk,
@@ -35,8 +76,14 @@ enum Workload {
         size: usize,
     },
     Spec2017CactuBSSN,
+    Spec2017Lbm,
+    Spec2017Bwaves,
+    Spec2017Roms,
+    Spec2017Fotonik3d,
+    Spec2017Omnetpp,
     Canneal {
         workload: CannealWorkload,
+        threads: usize,
     },
     AllocTest {
         size: usize,
@@ -48,6 +95,7 @@ enum Workload {
     Gups {
         threads: usize,
         exp: usize,
+        variant: GupsVariant,
         hot_exp: Option<usize>,
         move_hot: bool,
         num_updates: usize,
@@ -60,16 +108,148 @@ enum Workload {
         op_count: usize,
         read_prop: f32,
         update_prop: f32,
+        load_only: bool,
+        reuse_load: bool,
+        field_count: Option<usize>,
+        field_length: Option<usize>,
+        run_threads: Option<usize>,
     },
     Postgres {
         op_count: usize,
     },
+    Redis {
+        size: usize,
+        op_count: usize,
+        read_prop: f32,
+        update_prop: f32,
+    },
     Graph500 {
         size: usize,
     },
+    Rocksdb {
+        benchmarks: String,
+        num: usize,
+        value_size: usize,
+    },
     Stream {
         threads: usize,
     },
+    FileRead {
+        file_size_gb: usize,
+        threads: usize,
+        pattern: FileReadPattern,
+    },
+    Gap {
+        kernel: GapKernel,
+        scale: usize,
+        threads: usize,
+        trials: Option<usize>,
+    },
+    CacheBench {
+        max_size: usize,
+        stride: usize,
+    },
+    Multichase {
+        mem_size: usize,
+        chase: String,
+    },
+    Xsbench {
+        size: String,
+        lookups: usize,
+        threads: usize,
+    },
+    Idle {
+        duration: usize,
+    },
+    Custom {
+        binary: String,
+        args: Vec<String>,
+        cwd: Option<String>,
+        proc_name: String,
+        threads: usize,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+enum GupsVariant {
+    Basic,
+    Hotset,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+enum FileReadPattern {
+    Sequential,
+    Random,
+}
+
+impl Workload {
+    /// The name `runcpu` uses for a SPEC workload, or `None` if this isn't SPEC.
+    fn spec_benchmark(&self, rate: bool) -> Option<&'static str> {
+        let (speed, rate_name) = match self {
+            Workload::Spec2017Mcf => ("605.mcf_s", "505.mcf_r"),
+            Workload::Spec2017Xalancbmk => ("623.xalancbmk_s", "523.xalancbmk_r"),
+            Workload::Spec2017Xz { .. } => ("657.xz_s", "557.xz_r"),
+            Workload::Spec2017CactuBSSN => ("607.cactuBSSN_s", "507.cactuBSSN_r"),
+            Workload::Spec2017Lbm => ("619.lbm_s", "519.lbm_r"),
+            Workload::Spec2017Bwaves => ("603.bwaves_s", "503.bwaves_r"),
+            Workload::Spec2017Roms => ("654.roms_s", "554.roms_r"),
+            Workload::Spec2017Fotonik3d => ("649.fotonik3d_s", "549.fotonik3d_r"),
+            Workload::Spec2017Omnetpp => ("620.omnetpp_s", "520.omnetpp_r"),
+            _ => return None,
+        };
+
+        Some(if rate { rate_name } else { speed })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+enum GapKernel {
+    Bfs,
+    PageRank,
+    ConnectedComponents,
+    BetweennessCentrality,
+    Sssp,
+    TriangleCount,
+}
+
+impl GapKernel {
+    /// The name of the GAP binary for this kernel.
+    fn binary(&self) -> &'static str {
+        match self {
+            GapKernel::Bfs => "bfs",
+            GapKernel::PageRank => "pr",
+            GapKernel::ConnectedComponents => "cc",
+            GapKernel::BetweennessCentrality => "bc",
+            GapKernel::Sssp => "sssp",
+            GapKernel::TriangleCount => "tc",
+        }
+    }
+
+    /// SSSP needs a graph with edge weights, which GAP keeps in a different format.
+    fn weighted(&self) -> bool {
+        matches!(self, GapKernel::Sssp)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+enum SpecTune {
+    Base,
+    Peak,
+}
+
+/// Which sample of the workload's numa_maps --numa_maps_snapshot keeps.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+enum NumaMapsAt {
+    PeakRss,
+    End,
+}
+
+/// The stages of a run where --inject_failure can make it fail.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum FailureStage {
+    Mount,
+    Workload,
+    Collect,
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -78,13 +258,54 @@ struct MemRegion {
     start: usize,
 }
 
+impl MemRegion {
+    /// The end of the region, exclusive.
+    fn end(&self) -> usize {
+        self.start + self.size
+    }
+
+    /// Whether the [start, end) ranges of the two regions intersect.
+    fn overlaps(&self, other: &MemRegion) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 enum MMFS {
     Ext4,
+    Xfs,
     BasicMMFS { num_pages: usize },
     TieredMMFS,
     ContigMMFS,
     BandwidthMMFS,
+    Tmpfs { size_gb: usize },
+}
+
+impl MMFS {
+    /// The type of the FS as `findmnt` reports it.
+    fn fs_type(&self) -> &'static str {
+        match self {
+            MMFS::Ext4 => "ext4",
+            MMFS::Xfs => "xfs",
+            MMFS::BasicMMFS { .. } => "BasicMMFS",
+            MMFS::TieredMMFS => "TieredMMFS",
+            MMFS::ContigMMFS => "ContigMMFS",
+            MMFS::BandwidthMMFS => "BandwidthMMFS",
+            MMFS::Tmpfs { .. } => "tmpfs",
+        }
+    }
+
+    /// The name of the kernel module of the FS and its path under the kernel tree, if it is
+    /// not built in.
+    fn kernel_module(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            MMFS::BasicMMFS { .. } => Some(("basicmmfs", "BasicMMFS/basicmmfs.ko")),
+            MMFS::TieredMMFS => Some(("tieredmmfs", "TieredMMFS/tieredmmfs.ko")),
+            MMFS::ContigMMFS => Some(("contigmmfs", "ContigMMFS/contigmmfs.ko")),
+            MMFS::BandwidthMMFS => Some(("bandwidth", "BandwidthMMFS/bandwidth.ko")),
+            MMFS::Ext4 | MMFS::Xfs | MMFS::Tmpfs { .. } => None,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -93,6 +314,51 @@ struct NodeWeight {
     weight: u32,
 }
 
+/// Parse a node weight of the form "<nid>:<weight>".
+fn parse_node_weight(s: &str) -> Result<NodeWeight, failure::Error> {
+    let mut fields = s.split(':');
+    let (nid, weight) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(nid), Some(weight), None) => (nid, weight),
+        _ => {
+            return Err(failure::format_err!(
+                "Invalid --node_weight \"{}\". Expected <nid>:<weight>",
+                s
+            ))
+        }
+    };
+    let nid = nid
+        .parse::<u32>()
+        .map_err(|e| failure::format_err!("Invalid nid in --node_weight \"{}\": {}", s, e))?;
+    let weight = weight
+        .parse::<u32>()
+        .map_err(|e| failure::format_err!("Invalid weight in --node_weight \"{}\": {}", s, e))?;
+
+    Ok(NodeWeight { nid, weight })
+}
+
+fn is_node_weight(s: String) -> Result<(), String> {
+    parse_node_weight(&s).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Parse all of the --node_weight values, each of which must be for a different node.
+fn parse_node_weights<'a>(
+    weights: impl Iterator<Item = &'a str>,
+) -> Result<Vec<NodeWeight>, failure::Error> {
+    let node_weights = weights
+        .map(parse_node_weight)
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, weight) in node_weights.iter().enumerate() {
+        if node_weights[..i].iter().any(|w| w.nid == weight.nid) {
+            return Err(failure::format_err!(
+                "--node_weight is given more than once for node {}",
+                weight.nid
+            ));
+        }
+    }
+
+    Ok(node_weights)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Parametrize)]
 struct Config {
     #[name]
@@ -101,25 +367,84 @@ struct Config {
     #[name]
     workload: Workload,
 
+    labels: std::collections::BTreeMap<String, String>,
+
     perf_stat: bool,
     perf_periodic: bool,
     perf_counters: Vec<String>,
+    perf_stat_derived: bool,
+    workload_env: Vec<String>,
+    before_cmd: Option<String>,
+    after_cmd: Option<String>,
+    power_cycle_cmd: Option<String>,
+    power_cycle_timeout: u64,
+    reboot_initial_sleep: u64,
+    reboot_timeout: Option<u64>,
+    reboot_retries: Option<usize>,
+    inject_failure: Option<FailureStage>,
     disable_thp: bool,
+    khugepaged_scan_sleep: Option<usize>,
+    khugepaged_alloc_sleep: Option<usize>,
+    khugepaged_max_ptes_none: Option<usize>,
     disable_aslr: bool,
+    per_proc_no_aslr: bool,
+    mlock: bool,
     mm_fault_tracker: bool,
     mmap_tracker: bool,
     flame_graph: bool,
+    offcpu_flame_graph: bool,
+    perf_mem: bool,
+    perf_c2c: bool,
+    perf_switch_output: Option<String>,
+    strace_summary: bool,
     smaps_periodic: bool,
+    numa_maps_snapshot: Option<NumaMapsAt>,
+    zoneinfo: bool,
+    meminfo_periodic: bool,
+    numastat_periodic: bool,
+    vmstat_periodic: bool,
+    fraginfo_periodic: bool,
+    rapl: bool,
+    mbm: bool,
+    damon: Option<(usize, usize)>,
+    ftrace: Option<String>,
+    ftrace_buf_size_kb: Option<usize>,
+    sched_stats: bool,
+    wait_rss_gb: Option<usize>,
     tmmfs_stats_periodic: bool,
+    tier_occupancy_periodic: bool,
     tmmfs_active_list_periodic: bool,
+    active_list_max_mb: Option<usize>,
     lock_stat: bool,
+    abort_on_dmesg_warn: bool,
+    assume_setup: bool,
+    no_reboot: bool,
+    reuse_mount: bool,
+    fetch_results: Option<String>,
+    results_git: Option<String>,
+    results_git_push: bool,
+    isolcpus: bool,
+    reserve_cpuset: bool,
+    metrics_port: Option<u16>,
+    spec_tune: Option<SpecTune>,
+    spec_rate: Option<usize>,
+    page_reporting: Option<bool>,
+    force_migrate_period: Option<usize>,
+    sample_period: usize,
+    stress_ng_vm: Option<usize>,
+    stress_ng_vm_bytes: String,
+    instances_per_node: bool,
     fbmm: Option<MMFS>,
     fbmm_control: bool,
     tpp: bool,
+    disable_numa_balancing: bool,
+    no_turbo: bool,
     hmsdk_bw: bool,
     hmsdk_tiered: bool,
     dram_region: Option<MemRegion>,
     pmem_region: Option<MemRegion>,
+    fast_dev: String,
+    slow_dev: String,
     node_weights: Vec<NodeWeight>,
     numactl: bool,
     badger_trap: bool,
@@ -128,6 +453,9 @@ struct Config {
     numa_scan_delay: Option<usize>,
     numa_scan_period_min: Option<usize>,
     hugetlb: Option<usize>,
+    hugepages_boot: Option<usize>,
+    mem_high: Option<usize>,
+    mem_max: Option<usize>,
     pte_fault_size: Option<usize>,
 
     thp_temporal_zero: bool,
@@ -136,6 +464,7 @@ struct Config {
     track_pfn_insert: bool,
     mark_inode_dirty: bool,
     ext4_metadata: bool,
+    xfs_metadata: bool,
     no_prealloc: bool,
 
     username: String,
@@ -181,13 +510,26 @@ pub fn cli_options() -> clap::App<'static, 'static> {
                 (@arg NATIVE: --native
                  "Use the native workload.")
             )
+            (@arg THREADS: --threads +takes_value {validator::is::<usize>}
+             "The number of threads to run canneal with. Default: 1")
         )
         (@subcommand spec17 =>
             (about: "Run a spec workload on cloudlab")
             (@arg WHICH: +required
+             possible_values(&[
+                "mcf", "xalancbmk", "xz", "cactubssn", "lbm", "bwaves", "roms", "fotonik3d",
+                "omnetpp"
+             ])
              "Which spec worklosd to run.")
             (@arg SIZE: --spec_size +takes_value {validator::is::<usize>}
              "The size of the spec workload input.")
+            (@arg SPEC_TUNE: --spec_tune +takes_value possible_values(&["base", "peak"])
+             "The SPEC tuning to build and run with. Peak runs always go through runcpu with the \
+             ref input, so --spec_size does not apply to them. Default: base")
+            (@arg RATE: --rate +takes_value {validator::is::<usize>}
+             "(Optional) Run the rate (_r) version of the benchmark with this many copies, \
+             each pinned to its own core, instead of the speed (_s) version. --spec_size is \
+             ignored for rate runs.")
         )
         (@subcommand gups =>
             (about: "Run the GUPS workload used to eval HeMem")
@@ -202,6 +544,9 @@ pub fn cli_options() -> clap::App<'static, 'static> {
              "The log of the size of the hot region, if there is one")
             (@arg NUM_UPDATES: +takes_value {validator::is::<usize>}
              "The number of updates to do. Default is 2^exp / 8")
+            (@arg GUPS_VARIANT: --gups_variant +takes_value possible_values(&["basic", "hotset"])
+             "Which GUPS binary to run. Default: hotset if HOT_EXP is passed, basic otherwise. \
+             The hotset variant without HOT_EXP uses a hotset of 2^0.")
         )
         (@subcommand pagewalk_coherence =>
             (about: "Run the ubmk from https://blog.stuffedcow.net/2015/08/pagewalk-coherence/\
@@ -221,12 +566,46 @@ pub fn cli_options() -> clap::App<'static, 'static> {
             (@arg OP_COUNT: --op_count +takes_value {validator::is::<usize>}
              "The number of operations to perform during the workload.\
              The default is 1000.")
+            (@arg OPS_PER_RECORD: --ops_per_record +takes_value {validator::is::<f64>}
+             conflicts_with[OP_COUNT]
+             "Set the number of operations to this multiple of the number of records, so the \
+             operation count scales with the size of the workload.")
             (@arg READ_PROP: --read_prop +takes_value {validator::is::<f32>}
              "The proportion of read operations to perform as a value between 0 and 1.\
              The default is 0.5. The proportion on insert operations will be 1 - read_prop - update_prop.")
             (@arg UPDATE_PROP: --update_prop +takes_value {validator::is::<f32>}
              "The proportion of read operations to perform as a value between 0 and 1.\
              The default is 0.5. The proportion on insert operations will be 1 - read_prop - update_prop")
+            (@arg LOAD_ONLY: --load_only conflicts_with[REUSE_LOAD]
+             "Only start memcached and run the YCSB load phase, then leave the loaded server \
+             running so later --reuse_load runs can skip the load. The server lives in memory, so \
+             only a `batch`, or runs with --reuse_load, which skip the reboot, keep it. A failed \
+             or interrupted --load_only run stops it.")
+            (@arg REUSE_LOAD: --reuse_load conflicts_with[LOAD_ONLY]
+             "Skip the load phase and run YCSB against the server left by a previous --load_only \
+             run. The loaded cache lives in memory, so the remote is not rebooted and the FBMM \
+             mount is not recreated. The SIZE must match the --load_only run.")
+            (@arg YCSB_FIELD_COUNT: --ycsb_field_count +takes_value {validator::is::<usize>}
+             "The number of fields in each YCSB record. The YCSB default is 10.")
+            (@arg YCSB_FIELD_LENGTH: --ycsb_field_length +takes_value {validator::is::<usize>}
+             "The length in bytes of each field in a YCSB record. The YCSB default is 100.")
+            (@arg YCSB_RUN_THREADS: --ycsb_run_threads +takes_value {validator::is::<usize>}
+             "The number of YCSB client threads for the run phase, each pinned to its own core \
+             after the server's. The load phase is unaffected. The default is 1.")
+        )
+        (@subcommand redis =>
+            (about: "Run the redis workload driven by YCSB")
+            (@arg SIZE: +required +takes_value {validator::is::<usize>}
+             "The number of GBs for the workload.")
+            (@arg OP_COUNT: --op_count +takes_value {validator::is::<usize>}
+             "The number of operations to perform during the workload.\
+             The default is 1000.")
+            (@arg READ_PROP: --read_prop +takes_value {validator::is::<f32>}
+             "The proportion of read operations to perform as a value between 0 and 1.\
+             The default is 0.5. The proportion on insert operations will be 1 - read_prop - update_prop.")
+            (@arg UPDATE_PROP: --update_prop +takes_value {validator::is::<f32>}
+             "The proportion of update operations to perform as a value between 0 and 1.\
+             The default is 0.5. The proportion on insert operations will be 1 - read_prop - update_prop")
         )
         (@subcommand postgres =>
             (about: "Run the postgres workload driven by YCSB")
@@ -239,11 +618,89 @@ pub fn cli_options() -> clap::App<'static, 'static> {
             (@arg SIZE: +required +takes_value {validator::is::<usize>}
              "2^size nodes will be used for the workload.")
         )
+        (@subcommand rocksdb =>
+            (about: "Run RocksDB's db_bench. The DB is on the FBMM mount if using FBMM.")
+            (@arg BENCHMARKS: --benchmarks +takes_value
+             "The comma separated db_bench benchmarks to run. Default: fillrandom,readrandom")
+            (@arg NUM: --num +takes_value {validator::is::<usize>}
+             "The number of keys. Default: 1000000")
+            (@arg VALUE_SIZE: --value_size +takes_value {validator::is::<usize>}
+             "The size of each value in bytes. Default: 100")
+        )
         (@subcommand stream =>
             (about: "Run the STREAM ubmk")
             (@arg THREADS: --threads +takes_value {validator::is::<usize>}
              "The number of threads to run GUPS with. Default: 1")
         )
+        (@subcommand fileread =>
+            (about: "Create a file on the FBMM mount, then mmap it read-only and read through it.")
+            (@arg SIZE: +required +takes_value {validator::is::<usize>}
+             "The size of the file in GB.")
+            (@arg THREADS: --threads +takes_value {validator::is::<usize>}
+             "The number of threads to read the file with. Default: 1")
+            (@arg RANDOM: --random
+             "Read the pages of the file in a random order instead of sequentially.")
+        )
+        (@subcommand gap =>
+            (about: "Run a kernel from the GAP benchmark suite on a generated Kronecker graph.")
+            (@arg KERNEL: +required +takes_value
+             possible_values(&["bfs", "pr", "cc", "bc", "sssp", "tc"])
+             "The GAP kernel to run.")
+            (@arg SCALE: +required +takes_value {validator::is::<usize>}
+             "The graph will have 2^scale vertices.")
+            (@arg THREADS: --threads +takes_value {validator::is::<usize>}
+             "The number of threads to run the kernel with. Default: 1")
+            (@arg TRIALS: --trials +takes_value {validator::is::<usize>}
+             "The number of times to run the kernel. Default: GAP's default for the kernel")
+        )
+        (@subcommand cachebench =>
+            (about: "Measure the latency of pointer chasing as the working set grows to find \
+             the latency of each level of the cache hierarchy.")
+            (@arg MAX_SIZE: +required +takes_value {validator::is::<usize>}
+             "The largest working set to measure in MB.")
+            (@arg STRIDE: --stride +takes_value {validator::is::<usize>}
+             "The distance in bytes between the pointers. Default: 64")
+        )
+        (@subcommand idle =>
+            (about: "Sleep under the full instrumentation instead of running a workload, to \
+             measure the overhead of the collectors on a quiet machine.")
+            (@arg DURATION: +required +takes_value {validator::is::<usize>}
+             "How many seconds to sleep.")
+        )
+        (@subcommand xsbench =>
+            (about: "Run the XSBench Monte Carlo neutron transport proxy app.")
+            (@arg SIZE: --size +takes_value possible_values(&["small", "large", "XL", "XXL"])
+             "The size of the problem. Default: large")
+            (@arg LOOKUPS: --lookups +takes_value {validator::is::<usize>}
+             "The number of cross section lookups. Default: 17000000")
+            (@arg THREADS: --threads +takes_value {validator::is::<usize>}
+             "The number of threads to run XSBench with. Default: 1")
+        )
+        (@subcommand multichase =>
+            (about: "Measure the load-to-use latency of a pointer chase with multichase.")
+            (@arg MEM_SIZE: +required +takes_value {validator::is::<usize>}
+             "The size of the memory to chase through in MB.")
+            (@arg CHASE: --chase +takes_value
+             "The multichase traversal to use, e.g. simple or work:N. Default: simple")
+        )
+        (@subcommand custom =>
+            (about: "Run an arbitrary binary that is already on the remote as the workload.")
+            (@arg BINARY: +required +takes_value
+             "The path of the binary on the remote.")
+            (@arg ARGS: +takes_value ... +last
+             "The arguments to run the binary with, after a `--`.")
+            (@arg CWD: --cwd +takes_value
+             "(Optional) The directory on the remote to run the binary in. Default: home")
+            (@arg PROC_NAME: --proc_name +takes_value
+             "The name of the process as the collectors should find it with `pgrep -x`. Note \
+             that Linux truncates process names to 15 characters. Default: the first 15 \
+             characters of the binary's name")
+            (@arg THREADS: --threads +takes_value {validator::is::<usize>}
+             "The number of cores to pin the workload to. Default: 1")
+        )
+        (@arg EXP_NAME: --exp_name +takes_value
+         "The name of the experiment, which starts the names of the results files. \
+         Default: fbmm_exp")
         (@arg PERF_STAT: --perf_stat
          "Attach perf stat to the workload.")
         (@arg PERF_PERIODIC: --perf_periodic
@@ -252,30 +709,212 @@ pub fn cli_options() -> clap::App<'static, 'static> {
         (@arg PERF_COUNTER: --perf_counter +takes_value ... number_of_values(1)
          requires[PERF_STAT]
          "Which counters to record with perf stat.")
+        (@arg PERF_STAT_DERIVED: --perf_stat_derived
+         requires[PERF_STAT]
+         "Also record the counters perf stat needs to report IPC, branch miss rate, and cache \
+         miss rate.")
+        (@arg WORKLOAD_ENV: --workload_env +takes_value ... number_of_values(1)
+         "An environment variable to run the workload with, in the form KEY=VAL.")
+        (@arg LABEL: --label +takes_value ... number_of_values(1)
+         "A label to tag this run with in the params and summary, in the form KEY=VAL.")
+        (@arg BEFORE_CMD: --before_cmd +takes_value
+         "(Optional) A shell command to run on the remote right before the workload.")
+        (@arg AFTER_CMD: --after_cmd +takes_value
+         "(Optional) A shell command to run on the remote right after the workload.")
+        (@arg POWER_CYCLE_CMD: --power_cycle_cmd +takes_value
+         "(Optional) A command to run on this machine to power cycle the remote if it does not \
+         come back from a reboot. {host} is replaced with the remote's hostname.")
+        (@arg POWER_CYCLE_TIMEOUT: --power_cycle_timeout +takes_value {validator::is::<u64>}
+         requires[POWER_CYCLE_CMD]
+         "The number of seconds to wait for a reboot before power cycling. Default: 600")
+        (@arg REBOOT_INITIAL_SLEEP: --reboot_initial_sleep +takes_value {validator::is::<u64>}
+         "The number of seconds to wait after `sudo reboot` before trying to reconnect. \
+         Default: 5")
+        (@arg REBOOT_TIMEOUT: --reboot_timeout +takes_value {validator::is::<u64>}
+         "(Optional) Fail the run if the remote hasn't come back this many seconds after the \
+         reboot.")
+        (@arg REBOOT_RETRIES: --reboot_retries +takes_value {validator::is::<usize>}
+         "(Optional) Fail the run after this many failed attempts to reconnect after the \
+         reboot.")
+        (@arg INJECT_FAILURE: --inject_failure +takes_value +hidden
+         possible_values(&["mount", "workload", "collect"])
+         "Fail the run at the given stage to test the runner's error handling.")
         (@arg DISABLE_THP: --disable_thp
          "Disable THP completely.")
+        (@arg KHUGEPAGED_SCAN_SLEEP: --khugepaged_scan_sleep +takes_value {validator::is::<usize>}
+         conflicts_with[DISABLE_THP]
+         "The time in ms khugepaged sleeps between scans. Default: 1000")
+        (@arg KHUGEPAGED_ALLOC_SLEEP: --khugepaged_alloc_sleep +takes_value {validator::is::<usize>}
+         conflicts_with[DISABLE_THP]
+         "The time in ms khugepaged sleeps after a failed huge page allocation. Default: 1000")
+        (@arg KHUGEPAGED_MAX_PTES_NONE: --khugepaged_max_ptes_none +takes_value {validator::is::<usize>}
+         conflicts_with[DISABLE_THP]
+         "The number of unmapped pages khugepaged allows when collapsing a huge page. \
+         Default: the kernel default")
+        (@arg MLOCK: --mlock
+         "Lock all of the workload's memory with mlockall through an LD_PRELOAD shim, so \
+         reclaim never touches it. Every workload honors this, but for memcached and postgres \
+         only the server is locked, not YCSB. With --reuse_load, memcached keeps the setting \
+         of the --load_only run.")
         (@arg DISABLE_ASLR: --disable_aslr
          "Disable ASLR.")
+        (@arg PER_PROC_NO_ASLR: --per_proc_no_aslr conflicts_with[DISABLE_ASLR]
+         "Disable ASLR for only the workload with setarch -R, leaving the system-wide setting \
+         alone. This is friendlier to other users of shared machines.")
         (@arg MM_FAULT_TRACKER: --mm_fault_tracker
-         "Record page fault statistics with mm_fault_tracker.")
+         "Record page fault statistics with mm_fault_tracker. It has to be attached before the \
+         workload starts, so it follows the workload by process name rather than by PID.")
         (@arg MMAP_TRACKER: --mmap_tracker
-         "Record page fault statistics with mmap_tracker.")
+         "Record page fault statistics with mmap_tracker. It has to be attached before the \
+         workload starts, so it follows the workload by process name rather than by PID.")
         (@arg FLAME_GRAPH: --flame_graph
          "Generate a flame graph of the workload.")
+        (@arg OFFCPU_FLAME_GRAPH: --offcpu_flame_graph
+         "Generate a flame graph of the time the workload spends blocked, from bcc's \
+         offcputime.")
+        (@arg PERF_MEM: --perf_mem conflicts_with[FLAME_GRAPH] conflicts_with[PERF_C2C]
+         conflicts_with[STRACE_SUMMARY]
+         "Sample the memory accesses on the workload's cores with `perf mem record`, and save \
+         the perf.data and the report. The sampling overhead is high, so runtimes from these \
+         runs are not comparable to others.")
+        (@arg PERF_C2C: --perf_c2c conflicts_with[FLAME_GRAPH] conflicts_with[PERF_MEM]
+         conflicts_with[STRACE_SUMMARY]
+         "Record the cache line sharing on the workload's cores with `perf c2c record`, and \
+         save the perf.data and the report. The sampling overhead is high, so runtimes from \
+         these runs are not comparable to others.")
+        (@arg PERF_SWITCH_OUTPUT: --perf_switch_output +takes_value requires[FLAME_GRAPH]
+         "Start a new perf.data every time this much time (e.g. 10m) or data (e.g. 1G) is \
+         recorded, and make a flame graph for each one. Useful for long workloads.")
+        (@arg STRACE_SUMMARY: --strace_summary conflicts_with[FLAME_GRAPH]
+         "Run the workload under strace -c to count the time spent in each syscall. This \
+         slows the workload down a lot, so it is only for diagnostic runs.")
         (@arg SMAPS_PERIODIC: --smaps_periodic
          "Collect /proc/[PID]/smaps data periodically for the workload process")
+        (@arg NUMA_MAPS_SNAPSHOT: --numa_maps_snapshot
+         "Capture /proc/[PID]/numa_maps of the workload process at its peak RSS, or at the \
+         end of the run with --numa_maps_at end.")
+        (@arg NUMA_MAPS_AT: --numa_maps_at +takes_value possible_values(&["peak", "end"])
+         requires[NUMA_MAPS_SNAPSHOT]
+         "When to capture the numa_maps for --numa_maps_snapshot. It is sampled periodically, \
+         so \"end\" is the last sample before the process exited. Default: peak")
+        (@arg ZONEINFO: --zoneinfo
+         "Capture /proc/zoneinfo before and after the workload.")
+        (@arg MEMINFO_PERIODIC: --meminfo_periodic
+         "Collect /proc/meminfo periodically, with the time of each sample.")
+        (@arg NUMASTAT_PERIODIC: --numastat_periodic
+         "Collect the per-node memory usage from `numastat -m` periodically, with the time of \
+         each sample.")
+        (@arg VMSTAT_PERIODIC: --vmstat_periodic
+         "Collect /proc/vmstat periodically, with the time of each sample. The snapshot after \
+         the workload is still taken.")
+        (@arg FRAGINFO_PERIODIC: --fraginfo_periodic
+         "Collect /proc/buddyinfo and /proc/pagetypeinfo periodically, with the time of each \
+         sample, to see how fragmented memory is over time.")
+        (@arg RAPL: --rapl
+         "Record the package and DRAM energy used during the workload, and the average power, \
+         from the Intel RAPL counters.")
+        (@arg MBM: --mbm
+         "Record the total and local memory bandwidth used by the workload's cores during the \
+         workload with resctrl memory bandwidth monitoring. Requires resctrl to be mounted.")
+        (@arg DAMON: --damon
+         "Monitor the workload's accesses with DAMON, and record the access frequency of its \
+         regions periodically. Requires the DAMON sysfs interface.")
+        (@arg DAMON_SAMPLE_US: --damon_sample_us +takes_value {validator::is::<usize>}
+         requires[DAMON]
+         "The DAMON sampling interval in microseconds. Default: 5000")
+        (@arg DAMON_AGGR_US: --damon_aggr_us +takes_value {validator::is::<usize>}
+         requires[DAMON]
+         "The DAMON aggregation interval in microseconds. Default: 100000")
+        (@arg FTRACE: --ftrace +takes_value +allow_hyphen_values
+         "(Optional) Trace the kernel functions matching the given set_ftrace_filter (e.g., \
+         \"handle_mm_fault follow_page_mask\") with the function_graph tracer during the \
+         workload.")
+        (@arg FTRACE_BUF_SIZE_KB: --ftrace_buf_size_kb +takes_value {validator::is::<usize>}
+         requires[FTRACE]
+         "(Optional) The size of the per-CPU ftrace buffer in KB.")
+        (@arg SCHED_STATS: --sched_stats
+         "Record the context switches and CPU migrations of the workload process.")
+        (@arg WAIT_RSS_GB: --wait_rss_gb +takes_value {validator::is::<usize>}
+         "(Optional) Wait until the workload's RSS reaches this many GB before starting the \
+         periodic collectors. Servers (memcached, redis, postgres) are timed only once they \
+         reach it; for other workloads the runtime after reaching it is recorded separately.")
         (@arg TMMFS_STATS_PERIODIC: --tmmfs_stats_periodic
          requires[TIEREDMMFS]
          "Collect /sys/fs/tieredmmfs/stats data periodically.")
+        (@arg TIER_OCCUPANCY_PERIODIC: --tier_occupancy_periodic
+         requires[TIEREDMMFS]
+         "Periodically record how many pages TieredMMFS has in use in each tier, one \
+         `timestamp fast_pages slow_pages` line per sample. The run fails if the kernel's \
+         /sys/fs/tieredmmfs/stats does not report the pages of each tier.")
         (@arg TMMFS_ACTIVE_LIST_PERIODIC: --tmmfs_active_list_periodic
          requires[TIEREDMMFS]
          "Collect /sys/fs/tieredmmfs/active_list data periodically.")
+        (@arg ACTIVE_LIST_MAX_MB: --active_list_max_mb +takes_value {validator::is::<usize>}
+         requires[TMMFS_ACTIVE_LIST_PERIODIC]
+         "Stop collecting the active list once its output reaches this many MB, so it can't \
+         fill the disk. A line saying the collection was truncated is added to the output.")
         (@arg NUMACTL: --numactl
          "If passed, use numactl to make sure the workload only allocates from numa node 0.")
         (@arg BADGER_TRAP: --badger_trap
          "If passed, use badger trap to monitor the TLB misses of the workload.")
         (@arg LOCK_STAT: --lock_stat
          "Collect lock statistics from the workload.")
+        (@arg ABORT_ON_DMESG_WARN: --abort_on_dmesg_warn
+         "Check dmesg for kernel warnings or bugs after the workload, and fail the run if any are found.")
+        (@arg ASSUME_SETUP: --assume_setup
+         "Assume the remote is already booted, configured, and mounted as this run needs (e.g. \
+         from a previous run), and skip the grub edits, reboot, huge page reservation, and MMFS \
+         mount. The workload and collectors still run as usual.")
+        (@arg NO_REBOOT: --no_reboot conflicts_with[ASSUME_SETUP]
+         "Don't reboot the remote before the run. This fails if the kernel command line the \
+         remote booted with doesn't match what this run needs (memmap, tpp, huge pages, \
+         isolcpus).")
+        (@arg REUSE_MOUNT: --reuse_mount requires[FBMM]
+         "If daxtmp/ is still mounted with the same FS from a previous run on this boot (e.g., \
+         with --no_reboot), empty it and use it rather than formatting and mounting again. \
+         Only the FS type and device are checked, not its mount options. Without this, such \
+         a mount is an error. Whether it was reused is recorded in the mount results file.")
+        (@arg RESULTS_GIT: --results_git +takes_value
+         "(Optional) After the run, copy the results into the given git working tree on the remote \
+         and commit them.")
+        (@arg RESULTS_GIT_PUSH: --results_git_push
+         requires[RESULTS_GIT]
+         "Push the results commit made with --results_git.")
+        (@arg ISOLCPUS: --isolcpus
+         "Isolate the last N cores of the remote from the scheduler with isolcpus, nohz_full, \
+         and rcu_nocbs, and pin the workload to them, where N is the number of workload cores.")
+        (@arg RESERVE_CPUSET: --reserve_cpuset
+         "Run the periodic collectors and BPF trackers only on the cores not used by the \
+         workload, so they can never run on the measured cores.")
+        (@arg METRICS_PORT: --metrics_port +takes_value {validator::is::<u16>}
+         "(Optional) Serve the progress of the run in Prometheus format at /metrics on the given \
+         port of the driver machine.")
+        (@arg FORCE_MIGRATE_PERIOD: --force_migrate_period +takes_value {validator::is::<usize>}
+         "(Optional) Force a migration every given number of seconds during the workload. \
+         With TieredMMFS this triggers its migration task, otherwise it compacts memory.")
+        (@arg DRY_RUN: --dry_run
+         "Check the arguments and print the config, the results files, and the grub and mount \
+         commands of the run without connecting to the remote.")
+        (@arg FETCH_RESULTS: --fetch_results +takes_value
+         "(Optional) After the run, copy the results files of this run to the given directory \
+         on this machine. This is also done if the run fails.")
+        (@arg SAMPLE_PERIOD: --sample_period +takes_value {validator::is::<usize>}
+         "The period of the periodic collectors and perf stat -I in seconds. The active list \
+         is collected every 3 periods. Default: 10")
+        (@arg STRESS_NG_VM: --stress_ng_vm +takes_value {validator::is::<usize>}
+         "(Optional) Run this many stress-ng vm workers alongside the workload to add memory \
+         pressure. They are pinned to cores the workload doesn't use.")
+        (@arg STRESS_NG_VM_BYTES: --stress_ng_vm_bytes +takes_value requires[STRESS_NG_VM]
+         "The memory each stress-ng vm worker uses, in stress-ng's format (e.g. 4G or 10%). \
+         Default: 1G")
+        (@arg INSTANCES_PER_NODE: --instances_per_node
+         conflicts_with[NUMACTL] conflicts_with[ISOLCPUS] conflicts_with[HMSDK_BW]
+         "Run one copy of the workload on each NUMA node at the same time, each bound to the \
+         cores and memory of its node. Only supported for gups and stream.")
+        (@arg PAGE_REPORTING: --page_reporting +takes_value possible_values(&["on", "off"])
+         "(Optional) Turn free page reporting to the hypervisor on or off before the workload. \
+         Free page reporting is done by the virtio-balloon driver, so \"off\" unloads it. The run \
+         fails if the remote can't do what is asked.")
         (@arg FBMM: --fbmm
          requires[MMFS_TYPE] conflicts_with[TPP] conflicts_with[HUGETLB]
          "Run the workload with file based mm with the specified FS (either ext4 or TieredMMFS).")
@@ -285,6 +924,19 @@ pub fn cli_options() -> clap::App<'static, 'static> {
         (@arg TPP: --tpp
          requires[DRAM_SIZE] conflicts_with[FBMM] conflicts_with[HUGETLB]
          "Run the workload with TPP.")
+        (@arg DISABLE_NUMA_BALANCING: --disable_numa_balancing conflicts_with[TPP]
+         "Turn off AutoNUMA for the workload, so it doesn't confound the results. The previous \
+         setting is restored afterwards.")
+        (@arg NO_TURBO: --no_turbo
+         "Turn off turbo boost for the workload so the clock is stable. The previous setting \
+         is restored afterwards.")
+        (@arg MEM_HIGH: --mem_high +takes_value {validator::is::<usize>}
+         conflicts_with[HMSDK_TIERED]
+         "(Optional) Run the workload in a cgroup with this memory.high in GB, so it is \
+         throttled and reclaimed from above it.")
+        (@arg MEM_MAX: --mem_max +takes_value {validator::is::<usize>}
+         conflicts_with[HMSDK_TIERED]
+         "(Optional) Run the workload in a cgroup with this memory.max in GB.")
         (@group HMSDK_TYPE =>
             (@arg HMSDK_BW: --hmsdk_bw
              requires[NODE_WEIGHT]
@@ -296,6 +948,8 @@ pub fn cli_options() -> clap::App<'static, 'static> {
             (@attributes requires[FBMM])
             (@arg EXT4: --ext4
              "Use ext4 as the MM filesystem.")
+            (@arg XFS: --xfs
+             "Use XFS as the MM filesystem.")
             (@arg BASICMMFS: --basicmmfs +takes_value {validator::is::<usize>}
              "Use the BasicMMFS as the MM filesystem. Takes the number of pages it should reserve.")
             (@arg TIEREDMMFS: --tieredmmfs
@@ -305,6 +959,9 @@ pub fn cli_options() -> clap::App<'static, 'static> {
              "Use the ContgMMFS as the MM filesystem.")
             (@arg BWMMFS: --bwmmfs
              "Use the BandwidthMMFS as the MM filesystem.")
+            (@arg TMPFS: --tmpfs +takes_value {validator::is::<usize>}
+             "Use tmpfs in DRAM as the MM filesystem, as a baseline without DAX or PMEM. Takes \
+             the size of the tmpfs in GB.")
         )
         (@arg DRAM_SIZE: --dram_size +takes_value {validator::is::<usize>}
          "If passed, reserved the specifies amount of memory in GB as DRAM.")
@@ -317,7 +974,13 @@ pub fn cli_options() -> clap::App<'static, 'static> {
          requires[TIEREDMMFS]
          "If passed, specifies the starting point of the reserved PMEM in GB. \
          Default is dram_size + dram_start.")
+        (@arg FAST_DEV: --fast_dev +takes_value
+         "The pmem device ext4, XFS, and TieredMMFS use for the fast memory. \
+         Default: /dev/pmem0")
+        (@arg SLOW_DEV: --slow_dev +takes_value
+         "The pmem device TieredMMFS uses for the slow memory. Default: /dev/pmem1")
         (@arg NODE_WEIGHT: --node_weight +takes_value ... number_of_values(1)
+         {is_node_weight}
          "The node weights to use when using BWMMFS. Taken in the form of \"<nid>:<weight>\". \
          The default node weight is 1.")
         (@arg MIGRATE_TASK_INT: --migrate_task_int +takes_value {validator::is::<usize>}
@@ -331,6 +994,10 @@ pub fn cli_options() -> clap::App<'static, 'static> {
         (@arg HUGETLB: --hugetlb +takes_value {validator::is::<usize>}
          conflicts_with[FBMM] conflicts_with[TPP]
          "Run certain workloads with libhugetlbfs. Specify the number of huge pages to reserve in GB")
+        (@arg HUGEPAGES_BOOT: --hugepages_boot +takes_value {validator::is::<usize>}
+         conflicts_with[FBMM] conflicts_with[TPP] conflicts_with[HUGETLB]
+         "Like --hugetlb, but reserve the specified GB of huge pages on the kernel command line \
+         so the reservation happens at boot before memory is fragmented.")
         (@arg PTE_FAULT_SIZE: --pte_fault_size +takes_value {validator::is::<usize>}
          "The number of pages to allocate on a DAX pte fault.")
         (@arg THP_TEMPORAL_ZERO: --thp_temporal_zero
@@ -346,12 +1013,31 @@ pub fn cli_options() -> clap::App<'static, 'static> {
          "Tell the kernel to call the expensive mark_inode_dirty function.")
         (@arg EXT4_METADATA: --ext4_metadata
          "Have ext4 keep track of metadata, including checksums.")
+        (@arg XFS_METADATA: --xfs_metadata requires[XFS]
+         "Have XFS keep checksums of its metadata.")
         (@arg NO_PREALLOC: --no_prealloc
          "Do not preallocate memory on MAP_POPULATE.")
     }
 }
 
 pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
+    let cfg = parse_config(sub_m)?;
+
+    if sub_m.is_present("DRY_RUN") {
+        return print_plan(&cfg);
+    }
+
+    let login = Login {
+        username: &cfg.username,
+        hostname: &cfg.host,
+        host: cfg.host.as_str(),
+    };
+
+    run_and_fetch(&login, &cfg, false)
+}
+
+/// Build the experiment config from the arguments of the `fbmm_exp` subcommand.
+fn parse_config(sub_m: &clap::ArgMatches<'_>) -> Result<Config, failure::Error> {
     let login = Login {
         username: sub_m.value_of("USERNAME").unwrap(),
         hostname: sub_m.value_of("HOSTNAME").unwrap(),
@@ -392,8 +1078,13 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             } else {
                 CannealWorkload::Native
             };
+            let threads = sub_m
+                .value_of("THREADS")
+                .unwrap_or("1")
+                .parse::<usize>()
+                .unwrap();
 
-            Workload::Canneal { workload }
+            Workload::Canneal { workload, threads }
         }
 
         ("spec17", Some(sub_m)) => {
@@ -408,7 +1099,12 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
                 "xalancbmk" => Workload::Spec2017Xalancbmk,
                 "xz" => Workload::Spec2017Xz { size },
                 "cactubssn" => Workload::Spec2017CactuBSSN,
-                _ => panic!("Unknown spec workload"),
+                "lbm" => Workload::Spec2017Lbm,
+                "bwaves" => Workload::Spec2017Bwaves,
+                "roms" => Workload::Spec2017Roms,
+                "fotonik3d" => Workload::Spec2017Fotonik3d,
+                "omnetpp" => Workload::Spec2017Omnetpp,
+                _ => unreachable!(),
             }
         }
 
@@ -426,11 +1122,25 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             let num_updates = if let Some(updates_str) = sub_m.value_of("NUM_UPDATES") {
                 updates_str.parse::<usize>().unwrap()
             } else {
-                (1 << exp) / 8
+                default_gups_updates(exp)
             };
+            let variant = match sub_m.value_of("GUPS_VARIANT") {
+                Some("basic") => GupsVariant::Basic,
+                Some("hotset") => GupsVariant::Hotset,
+                _ if hot_exp.is_some() => GupsVariant::Hotset,
+                _ => GupsVariant::Basic,
+            };
+            if let GupsVariant::Basic = variant {
+                if hot_exp.is_some() || move_hot {
+                    return Err(failure::format_err!(
+                        "HOT_EXP and --move_hot can only be used with the hotset GUPS variant"
+                    ));
+                }
+            }
             Workload::Gups {
                 threads,
                 exp,
+                variant,
                 hot_exp,
                 move_hot,
                 num_updates,
@@ -448,6 +1158,54 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
         }
 
         ("memcached", Some(sub_m)) => {
+            let size = sub_m.value_of("SIZE").unwrap().parse::<usize>().unwrap();
+            let read_prop = sub_m
+                .value_of("READ_PROP")
+                .unwrap_or("0.5")
+                .parse::<f32>()
+                .unwrap();
+            let update_prop = sub_m
+                .value_of("UPDATE_PROP")
+                .unwrap_or("0.5")
+                .parse::<f32>()
+                .unwrap();
+            let load_only = sub_m.is_present("LOAD_ONLY");
+            let reuse_load = sub_m.is_present("REUSE_LOAD");
+            let field_count = sub_m
+                .value_of("YCSB_FIELD_COUNT")
+                .map(|v| v.parse::<usize>().unwrap());
+            let field_length = sub_m
+                .value_of("YCSB_FIELD_LENGTH")
+                .map(|v| v.parse::<usize>().unwrap());
+            let run_threads = sub_m
+                .value_of("YCSB_RUN_THREADS")
+                .map(|v| v.parse::<usize>().unwrap());
+            let record_count = checked_record_count("memcached", size, field_count, field_length)?;
+            let op_count = if let Some(ops_per_record) = sub_m.value_of("OPS_PER_RECORD") {
+                let ops_per_record = ops_per_record.parse::<f64>().unwrap();
+                (record_count as f64 * ops_per_record) as usize
+            } else {
+                sub_m
+                    .value_of("OP_COUNT")
+                    .unwrap_or("1000")
+                    .parse::<usize>()
+                    .unwrap()
+            };
+
+            Workload::Memcached {
+                size,
+                op_count,
+                read_prop,
+                update_prop,
+                load_only,
+                reuse_load,
+                field_count,
+                field_length,
+                run_threads,
+            }
+        }
+
+        ("redis", Some(sub_m)) => {
             let size = sub_m.value_of("SIZE").unwrap().parse::<usize>().unwrap();
             let op_count = sub_m
                 .value_of("OP_COUNT")
@@ -464,8 +1222,9 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
                 .unwrap_or("0.5")
                 .parse::<f32>()
                 .unwrap();
+            checked_record_count("redis", size, None, None)?;
 
-            Workload::Memcached {
+            Workload::Redis {
                 size,
                 op_count,
                 read_prop,
@@ -473,6 +1232,29 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             }
         }
 
+        ("rocksdb", Some(sub_m)) => {
+            let benchmarks = sub_m
+                .value_of("BENCHMARKS")
+                .unwrap_or("fillrandom,readrandom")
+                .into();
+            let num = sub_m
+                .value_of("NUM")
+                .unwrap_or("1000000")
+                .parse::<usize>()
+                .unwrap();
+            let value_size = sub_m
+                .value_of("VALUE_SIZE")
+                .unwrap_or("100")
+                .parse::<usize>()
+                .unwrap();
+
+            Workload::Rocksdb {
+                benchmarks,
+                num,
+                value_size,
+            }
+        }
+
         ("postgres", Some(sub_m)) => {
             let op_count = sub_m
                 .value_of("OP_COUNT")
@@ -498,25 +1280,287 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             Workload::Stream { threads }
         }
 
+        ("gap", Some(sub_m)) => {
+            let kernel = match sub_m.value_of("KERNEL").unwrap() {
+                "bfs" => GapKernel::Bfs,
+                "pr" => GapKernel::PageRank,
+                "cc" => GapKernel::ConnectedComponents,
+                "bc" => GapKernel::BetweennessCentrality,
+                "sssp" => GapKernel::Sssp,
+                "tc" => GapKernel::TriangleCount,
+                _ => unreachable!(),
+            };
+            let scale = sub_m.value_of("SCALE").unwrap().parse::<usize>().unwrap();
+            let threads = sub_m
+                .value_of("THREADS")
+                .unwrap_or("1")
+                .parse::<usize>()
+                .unwrap();
+
+            let trials = sub_m
+                .value_of("TRIALS")
+                .map(|trials| trials.parse::<usize>().unwrap());
+
+            Workload::Gap {
+                kernel,
+                scale,
+                threads,
+                trials,
+            }
+        }
+
+        ("cachebench", Some(sub_m)) => {
+            let max_size = sub_m
+                .value_of("MAX_SIZE")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap();
+            let stride = sub_m
+                .value_of("STRIDE")
+                .unwrap_or("64")
+                .parse::<usize>()
+                .unwrap();
+
+            Workload::CacheBench { max_size, stride }
+        }
+
+        ("idle", Some(sub_m)) => {
+            let duration = sub_m
+                .value_of("DURATION")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap();
+
+            Workload::Idle { duration }
+        }
+
+        ("xsbench", Some(sub_m)) => {
+            let size = sub_m.value_of("SIZE").unwrap_or("large").into();
+            let lookups = sub_m
+                .value_of("LOOKUPS")
+                .unwrap_or("17000000")
+                .parse::<usize>()
+                .unwrap();
+            let threads = sub_m
+                .value_of("THREADS")
+                .unwrap_or("1")
+                .parse::<usize>()
+                .unwrap();
+
+            Workload::Xsbench {
+                size,
+                lookups,
+                threads,
+            }
+        }
+
+        ("multichase", Some(sub_m)) => {
+            let mem_size = sub_m
+                .value_of("MEM_SIZE")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap();
+            let chase = sub_m.value_of("CHASE").unwrap_or("simple").into();
+
+            Workload::Multichase { mem_size, chase }
+        }
+
+        ("custom", Some(sub_m)) => {
+            let binary: String = sub_m.value_of("BINARY").unwrap().into();
+            let args = sub_m
+                .values_of("ARGS")
+                .map(|args| args.map(Into::into).collect())
+                .unwrap_or_default();
+            let cwd = sub_m.value_of("CWD").map(Into::into);
+            let proc_name = match sub_m.value_of("PROC_NAME") {
+                Some(proc_name) => proc_name.into(),
+                None => binary
+                    .rsplit('/')
+                    .next()
+                    .unwrap()
+                    .chars()
+                    .take(15)
+                    .collect(),
+            };
+            let threads = sub_m
+                .value_of("THREADS")
+                .unwrap_or("1")
+                .parse::<usize>()
+                .unwrap();
+
+            Workload::Custom {
+                binary,
+                args,
+                cwd,
+                proc_name,
+                threads,
+            }
+        }
+
+        ("fileread", Some(sub_m)) => {
+            let file_size_gb = sub_m.value_of("SIZE").unwrap().parse::<usize>().unwrap();
+            let threads = sub_m
+                .value_of("THREADS")
+                .unwrap_or("1")
+                .parse::<usize>()
+                .unwrap();
+            let pattern = if sub_m.is_present("RANDOM") {
+                FileReadPattern::Random
+            } else {
+                FileReadPattern::Sequential
+            };
+
+            Workload::FileRead {
+                file_size_gb,
+                threads,
+                pattern,
+            }
+        }
+
         _ => unreachable!(),
     };
 
     let perf_stat = sub_m.is_present("PERF_STAT");
     let perf_periodic = sub_m.is_present("PERF_PERIODIC");
+    let perf_stat_derived = sub_m.is_present("PERF_STAT_DERIVED");
     let disable_thp = sub_m.is_present("DISABLE_THP");
+    let khugepaged_scan_sleep = sub_m
+        .value_of("KHUGEPAGED_SCAN_SLEEP")
+        .map(|v| v.parse::<usize>().unwrap());
+    let khugepaged_alloc_sleep = sub_m
+        .value_of("KHUGEPAGED_ALLOC_SLEEP")
+        .map(|v| v.parse::<usize>().unwrap());
+    let khugepaged_max_ptes_none = sub_m
+        .value_of("KHUGEPAGED_MAX_PTES_NONE")
+        .map(|v| v.parse::<usize>().unwrap());
     let disable_aslr = sub_m.is_present("DISABLE_ASLR");
+    let per_proc_no_aslr = sub_m.is_present("PER_PROC_NO_ASLR");
+    let mlock = sub_m.is_present("MLOCK");
     let mm_fault_tracker = sub_m.is_present("MM_FAULT_TRACKER");
     let mmap_tracker = sub_m.is_present("MMAP_TRACKER");
     let flame_graph = sub_m.is_present("FLAME_GRAPH");
+    let offcpu_flame_graph = sub_m.is_present("OFFCPU_FLAME_GRAPH");
+    let perf_mem = sub_m.is_present("PERF_MEM");
+    let perf_c2c = sub_m.is_present("PERF_C2C");
+    let perf_switch_output = sub_m.value_of("PERF_SWITCH_OUTPUT").map(String::from);
+    if let Some(switch) = &perf_switch_output {
+        // perf takes a number followed by a time (s, m, h, d) or size (B, K, M, G) unit
+        let num = switch.trim_end_matches(|c| "smhdBKMG".contains(c));
+        if num.parse::<usize>().is_err() || switch.len() != num.len() + 1 {
+            return Err(failure::format_err!(
+                "Invalid --perf_switch_output \"{}\". Expected a time like 10m or a size like 1G",
+                switch
+            ));
+        }
+    }
+    let strace_summary = sub_m.is_present("STRACE_SUMMARY");
     let smaps_periodic = sub_m.is_present("SMAPS_PERIODIC");
+    let numa_maps_snapshot = if sub_m.is_present("NUMA_MAPS_SNAPSHOT") {
+        match sub_m.value_of("NUMA_MAPS_AT").unwrap_or("peak") {
+            "peak" => Some(NumaMapsAt::PeakRss),
+            "end" => Some(NumaMapsAt::End),
+            _ => unreachable!(),
+        }
+    } else {
+        None
+    };
+    let zoneinfo = sub_m.is_present("ZONEINFO");
+    let meminfo_periodic = sub_m.is_present("MEMINFO_PERIODIC");
+    let numastat_periodic = sub_m.is_present("NUMASTAT_PERIODIC");
+    let vmstat_periodic = sub_m.is_present("VMSTAT_PERIODIC");
+    let fraginfo_periodic = sub_m.is_present("FRAGINFO_PERIODIC");
+    let rapl = sub_m.is_present("RAPL");
+    let mbm = sub_m.is_present("MBM");
+    let damon = if sub_m.is_present("DAMON") {
+        let sample_us = sub_m
+            .value_of("DAMON_SAMPLE_US")
+            .unwrap_or("5000")
+            .parse::<usize>()
+            .unwrap();
+        let aggr_us = sub_m
+            .value_of("DAMON_AGGR_US")
+            .unwrap_or("100000")
+            .parse::<usize>()
+            .unwrap();
+        Some((sample_us, aggr_us))
+    } else {
+        None
+    };
+    let ftrace: Option<String> = sub_m.value_of("FTRACE").map(Into::into);
+    let ftrace_buf_size_kb = sub_m
+        .value_of("FTRACE_BUF_SIZE_KB")
+        .map(|kb| kb.parse::<usize>().unwrap());
+    if ftrace
+        .as_ref()
+        .map_or(false, |filter| filter.trim().is_empty())
+    {
+        println!("WARNING: the --ftrace filter is empty, so every kernel function will be traced");
+    }
+    let sched_stats = sub_m.is_present("SCHED_STATS");
+    let wait_rss_gb = sub_m
+        .value_of("WAIT_RSS_GB")
+        .map(|gb| gb.parse::<usize>().unwrap());
     let tmmfs_stats_periodic = sub_m.is_present("TMMFS_STATS_PERIODIC");
+    let tier_occupancy_periodic = sub_m.is_present("TIER_OCCUPANCY_PERIODIC");
     let tmmfs_active_list_periodic = sub_m.is_present("TMMFS_ACTIVE_LIST_PERIODIC");
+    let active_list_max_mb = sub_m
+        .value_of("ACTIVE_LIST_MAX_MB")
+        .map(|mb| mb.parse::<usize>().unwrap());
     let numactl = sub_m.is_present("NUMACTL");
     let lock_stat = sub_m.is_present("LOCK_STAT");
+    let abort_on_dmesg_warn = sub_m.is_present("ABORT_ON_DMESG_WARN");
+    let assume_setup = sub_m.is_present("ASSUME_SETUP");
+    let no_reboot = sub_m.is_present("NO_REBOOT");
+    let reuse_mount = sub_m.is_present("REUSE_MOUNT");
+    let fetch_results = sub_m.value_of("FETCH_RESULTS").map(Into::into);
+    let results_git = sub_m.value_of("RESULTS_GIT").map(Into::into);
+    let results_git_push = sub_m.is_present("RESULTS_GIT_PUSH");
+    let isolcpus = sub_m.is_present("ISOLCPUS");
+    let reserve_cpuset = sub_m.is_present("RESERVE_CPUSET");
+    let metrics_port = sub_m
+        .value_of("METRICS_PORT")
+        .map(|port| port.parse::<u16>().unwrap());
+    let page_reporting = sub_m.value_of("PAGE_REPORTING").map(|v| v == "on");
+    let instances_per_node = sub_m.is_present("INSTANCES_PER_NODE");
+    if instances_per_node && !matches!(workload, Workload::Gups { .. } | Workload::Stream { .. }) {
+        return Err(failure::format_err!(
+            "--instances_per_node is only supported for gups and stream"
+        ));
+    }
+    let force_migrate_period = sub_m
+        .value_of("FORCE_MIGRATE_PERIOD")
+        .map(|period| period.parse::<usize>().unwrap());
+    let sample_period = sub_m
+        .value_of("SAMPLE_PERIOD")
+        .map(|period| period.parse::<usize>().unwrap())
+        .unwrap_or(PERIOD);
+    let stress_ng_vm = sub_m
+        .value_of("STRESS_NG_VM")
+        .map(|workers| workers.parse::<usize>().unwrap());
+    let stress_ng_vm_bytes = sub_m.value_of("STRESS_NG_VM_BYTES").unwrap_or("1G").into();
+    let spec_tune = sub_m.subcommand_matches("spec17").map(|spec_m| {
+        match spec_m.value_of("SPEC_TUNE").unwrap_or("base") {
+            "base" => SpecTune::Base,
+            "peak" => SpecTune::Peak,
+            _ => unreachable!(),
+        }
+    });
+    let spec_rate = sub_m
+        .subcommand_matches("spec17")
+        .and_then(|spec_m| spec_m.value_of("RATE"))
+        .map(|copies| copies.parse::<usize>().unwrap());
     let badger_trap = sub_m.is_present("BADGER_TRAP");
+    let inject_failure = sub_m.value_of("INJECT_FAILURE").map(|stage| match stage {
+        "mount" => FailureStage::Mount,
+        "workload" => FailureStage::Workload,
+        "collect" => FailureStage::Collect,
+        _ => unreachable!(),
+    });
     let fbmm = sub_m.is_present("FBMM").then(|| {
         if sub_m.is_present("EXT4") {
             MMFS::Ext4
+        } else if sub_m.is_present("XFS") {
+            MMFS::Xfs
         } else if let Some(num_pages_str) = sub_m.value_of("BASICMMFS") {
             let num_pages = num_pages_str.parse::<usize>().unwrap();
             MMFS::BasicMMFS { num_pages }
@@ -526,12 +1570,17 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             MMFS::ContigMMFS
         } else if sub_m.is_present("BWMMFS") {
             MMFS::BandwidthMMFS
+        } else if let Some(size_gb) = sub_m.value_of("TMPFS") {
+            let size_gb = size_gb.parse::<usize>().unwrap();
+            MMFS::Tmpfs { size_gb }
         } else {
             panic!("Invalid MM file system. Use either --ext4 or --tieredmmfs");
         }
     });
     let fbmm_control = sub_m.is_present("FBMM_CONTROL");
     let tpp = sub_m.is_present("TPP");
+    let disable_numa_balancing = sub_m.is_present("DISABLE_NUMA_BALANCING");
+    let no_turbo = sub_m.is_present("NO_TURBO");
     let hmsdk_bw = sub_m.is_present("HMSDK_BW");
     let hmsdk_tiered = sub_m.is_present("HMSDK_TIERED");
     let dram_region = sub_m.is_present("DRAM_SIZE").then(|| {
@@ -552,6 +1601,8 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             start: dram_start,
         }
     });
+    let fast_dev = sub_m.value_of("FAST_DEV").unwrap_or("/dev/pmem0").into();
+    let slow_dev = sub_m.value_of("SLOW_DEV").unwrap_or("/dev/pmem1").into();
     let pmem_region = sub_m.is_present("PMEM_SIZE").then(|| {
         let pmem_size = sub_m
             .value_of("PMEM_SIZE")
@@ -569,21 +1620,19 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
             start: pmem_start,
         }
     });
-    let node_weights: Vec<NodeWeight> =
-        sub_m
-            .values_of("NODE_WEIGHT")
-            .map_or(Vec::new(), |counters| {
-                counters
-                    .map(|s| {
-                        // The format of a node weight is <nid>:<weight>
-                        let split: Vec<&str> = s.split(":").collect();
-                        let nid = split[0].parse::<u32>().unwrap();
-                        let weight = split[1].parse::<u32>().unwrap();
-
-                        NodeWeight { nid, weight }
-                    })
-                    .collect()
-            });
+    // Overlapping memmap regions leave the remote unbootable or silently misconfigured
+    if let (Some(dram), Some(pmem)) = (&dram_region, &pmem_region) {
+        if dram.overlaps(pmem) {
+            return Err(failure::format_err!(
+                "The DRAM region [{}G, {}G) overlaps the PMEM region [{}G, {}G)",
+                dram.start,
+                dram.end(),
+                pmem.start,
+                pmem.end()
+            ));
+        }
+    }
+    let node_weights = parse_node_weights(sub_m.values_of("NODE_WEIGHT").into_iter().flatten())?;
     let migrate_task_int = sub_m
         .value_of("MIGRATE_TASK_INT")
         .map(|interval| interval.parse::<usize>().unwrap());
@@ -599,6 +1648,15 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
     let hugetlb = sub_m
         .value_of("HUGETLB")
         .map(|huge_size| huge_size.parse::<usize>().unwrap());
+    let hugepages_boot = sub_m
+        .value_of("HUGEPAGES_BOOT")
+        .map(|huge_size| huge_size.parse::<usize>().unwrap());
+    let mem_high = sub_m
+        .value_of("MEM_HIGH")
+        .map(|gb| gb.parse::<usize>().unwrap());
+    let mem_max = sub_m
+        .value_of("MEM_MAX")
+        .map(|gb| gb.parse::<usize>().unwrap());
     let pte_fault_size = sub_m
         .value_of("PTE_FAULT_SIZE")
         .map(|v| v.parse::<usize>().unwrap());
@@ -609,43 +1667,138 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
     let mark_inode_dirty = sub_m.is_present("MARK_INODE_DIRTY");
     let no_prealloc = sub_m.is_present("NO_PREALLOC");
     let ext4_metadata = sub_m.is_present("EXT4_METADATA");
+    let xfs_metadata = sub_m.is_present("XFS_METADATA");
     let perf_counters: Vec<String> = sub_m
         .values_of("PERF_COUNTER")
         .map_or(Vec::new(), |counters| counters.map(Into::into).collect());
+    let before_cmd = sub_m.value_of("BEFORE_CMD").map(Into::into);
+    let after_cmd = sub_m.value_of("AFTER_CMD").map(Into::into);
+    let power_cycle_cmd = sub_m.value_of("POWER_CYCLE_CMD").map(Into::into);
+    let power_cycle_timeout = sub_m
+        .value_of("POWER_CYCLE_TIMEOUT")
+        .unwrap_or("600")
+        .parse::<u64>()
+        .unwrap();
+    let reboot_initial_sleep = sub_m
+        .value_of("REBOOT_INITIAL_SLEEP")
+        .unwrap_or("5")
+        .parse::<u64>()
+        .unwrap();
+    let reboot_timeout = sub_m
+        .value_of("REBOOT_TIMEOUT")
+        .map(|value| value.parse::<u64>().unwrap());
+    let reboot_retries = sub_m
+        .value_of("REBOOT_RETRIES")
+        .map(|value| value.parse::<usize>().unwrap());
+    let workload_env: Vec<String> = sub_m
+        .values_of("WORKLOAD_ENV")
+        .map_or(Vec::new(), |vars| vars.map(Into::into).collect());
+    if let Some(var) = workload_env.iter().find(|var| !var.contains('=')) {
+        return Err(failure::format_err!(
+            "Invalid --workload_env \"{}\". Expected KEY=VAL",
+            var
+        ));
+    }
 
-    let ushell = SshShell::with_any_key(login.username, login.host)?;
-    let remote_research_settings = libscail::get_remote_research_settings(&ushell)?;
+    let mut labels = std::collections::BTreeMap::new();
+    for label in sub_m.values_of("LABEL").into_iter().flatten() {
+        let (key, val) = label.split_once('=').ok_or_else(|| {
+            failure::format_err!("Invalid --label \"{}\". Expected KEY=VAL", label)
+        })?;
+        labels.insert(key.into(), val.into());
+    }
 
     let cfg = Config {
-        exp: "fom_exp".into(),
+        exp: sub_m.value_of("EXP_NAME").unwrap_or("fbmm_exp").into(),
         workload,
+        labels,
         perf_stat,
         perf_periodic,
         perf_counters,
+        perf_stat_derived,
+        workload_env,
+        before_cmd,
+        after_cmd,
+        power_cycle_cmd,
+        power_cycle_timeout,
+        reboot_initial_sleep,
+        reboot_timeout,
+        reboot_retries,
+        inject_failure,
         disable_thp,
+        khugepaged_scan_sleep,
+        khugepaged_alloc_sleep,
+        khugepaged_max_ptes_none,
         disable_aslr,
+        per_proc_no_aslr,
+        mlock,
         mm_fault_tracker,
         mmap_tracker,
         flame_graph,
+        offcpu_flame_graph,
+        perf_mem,
+        perf_c2c,
+        perf_switch_output,
+        strace_summary,
         smaps_periodic,
+        numa_maps_snapshot,
+        zoneinfo,
+        meminfo_periodic,
+        numastat_periodic,
+        vmstat_periodic,
+        fraginfo_periodic,
+        rapl,
+        mbm,
+        damon,
+        ftrace,
+        ftrace_buf_size_kb,
+        sched_stats,
+        wait_rss_gb,
         tmmfs_stats_periodic,
+        tier_occupancy_periodic,
         tmmfs_active_list_periodic,
+        active_list_max_mb,
         numactl,
         badger_trap,
         lock_stat,
+        abort_on_dmesg_warn,
+        assume_setup,
+        no_reboot,
+        reuse_mount,
+        fetch_results,
+        results_git,
+        results_git_push,
+        isolcpus,
+        reserve_cpuset,
+        metrics_port,
+        spec_tune,
+        spec_rate,
+        page_reporting,
+        force_migrate_period,
+        sample_period,
+        stress_ng_vm,
+        stress_ng_vm_bytes,
+        instances_per_node,
         fbmm,
         fbmm_control,
         tpp,
+        disable_numa_balancing,
+        no_turbo,
         hmsdk_bw,
         hmsdk_tiered,
         dram_region,
         pmem_region,
+        fast_dev,
+        slow_dev,
         node_weights,
         migrate_task_int,
         numa_scan_size,
         numa_scan_delay,
         numa_scan_period_min,
         hugetlb,
+        hugepages_boot,
+        mem_high,
+        mem_max,
         pte_fault_size,
 
         thp_temporal_zero,
@@ -654,29 +1807,512 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
         track_pfn_insert,
         mark_inode_dirty,
         ext4_metadata,
+        xfs_metadata,
         no_prealloc,
 
         username: login.username.into(),
         host: login.hostname.into(),
 
-        remote_research_settings,
+        // Read from the remote when the params file is written, so parsing doesn't connect
+        remote_research_settings: Default::default(),
 
         timestamp: Timestamp::now(),
     };
 
-    run_inner(&login, &cfg)
+    Ok(cfg)
 }
 
-fn empty_func(_: &SshShell) -> Result<(), ScailError> {
-    Ok(())
+/// Re-run the experiment described by the contents of a params file.
+pub(crate) fn retry(params: &str) -> Result<(), failure::Error> {
+    // The experiment name can be anything, so a params file from another kind of experiment
+    // is caught by it not having the fields of this Config
+    let mut cfg: Config = serde_json::from_str(params)
+        .map_err(|e| failure::format_err!("Not a params file from fbmm_exp: {}", e))?;
+
+    // Don't overwrite the results of the run being retried
+    cfg.timestamp = Timestamp::now();
+
+    let login = Login {
+        username: &cfg.username,
+        hostname: &cfg.host,
+        host: cfg.host.as_str(),
+    };
+
+    run_and_fetch(&login, &cfg, false)
 }
 
-fn run_inner<A>(login: &Login<A>, cfg: &Config) -> Result<(), failure::Error>
+fn set_teardown<A>(login: &Login<A>, cmds: Vec<String>)
+where
+    A: std::net::ToSocketAddrs + std::fmt::Display + std::fmt::Debug + Clone,
+{
+    *TEARDOWN.lock().unwrap() = Some((login.username.into(), login.hostname.into(), cmds));
+}
+
+/// Stop the processes and mounts of the current run on the remote, if there is one. Called
+/// from the Ctrl-C handler and when a run fails, so errors are printed rather than returned.
+pub(crate) fn teardown() {
+    let (username, host, cmds) = match TEARDOWN.lock().map(|mut teardown| teardown.take()) {
+        Ok(Some(teardown)) => teardown,
+        _ => return,
+    };
+
+    println!("Cleaning up the remote...");
+    let ushell = match SshShell::with_any_key(&username, &host) {
+        Ok(ushell) => ushell,
+        Err(e) => {
+            println!("WARNING: unable to connect to clean up the remote: {}", e);
+            return;
+        }
+    };
+
+    // Most of these fail because the process wasn't running, which is fine
+    for cmd in cmds {
+        let _ = ushell.run(cmd!("{}", cmd));
+    }
+}
+
+/// Run several experiments back to back, each given by the arguments of a `fbmm_exp` command.
+/// Only the first one reboots the remote. The rest reuse that boot, so they fail if they need
+/// different kernel command line options. If `failures_file` is given, a failed run is recorded
+/// in it and the batch goes on, failing at the end if any run failed.
+pub(crate) fn batch(
+    runs: &[Vec<String>],
+    failures_file: Option<&str>,
+) -> Result<(), failure::Error> {
+    // Check all of the arguments before starting anything
+    let mut cfgs = Vec::new();
+    for args in runs {
+        let matches = cli_options().get_matches_from_safe(
+            std::iter::once("fbmm_exp").chain(args.iter().map(String::as_str)),
+        )?;
+        if matches.is_present("DRY_RUN") {
+            return Err(failure::format_err!(
+                "--dry_run is not supported in a batch"
+            ));
+        }
+        cfgs.push(parse_config(&matches)?);
+    }
+
+    if let Some(cfg) = cfgs
+        .iter()
+        .find(|cfg| cfg.host != cfgs[0].host || cfg.username != cfgs[0].username)
+    {
+        return Err(failure::format_err!(
+            "All runs of a batch must use the same remote, but {}@{} differs from {}@{}",
+            cfg.username,
+            cfg.host,
+            cfgs[0].username,
+            cfgs[0].host
+        ));
+    }
+
+    let tear_down: Vec<bool> = (0..cfgs.len())
+        .map(|i| tear_down_between(&cfgs[i], cfgs.get(i + 1)))
+        .collect();
+    // Reboot until a run succeeds, since a failed run may not have booted the remote properly
+    let mut booted = false;
+    let mut failed = Vec::new();
+    for (i, mut cfg) in cfgs.into_iter().enumerate() {
+        if booted && !cfg.assume_setup {
+            cfg.no_reboot = true;
+        }
+        // The configs were all created at about the same time, so give each its own timestamp
+        cfg.timestamp = Timestamp::now();
+
+        let login = Login {
+            username: &cfg.username,
+            hostname: &cfg.host,
+            host: cfg.host.as_str(),
+        };
+
+        println!("Batch run {} of {}", i + 1, runs.len());
+        match (run_and_fetch(&login, &cfg, tear_down[i]), failures_file) {
+            (Ok(()), _) => booted = true,
+            (Err(e), Some(failures_file)) => {
+                println!("Batch run {} failed: {}", i + 1, e);
+                let mut failures = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(failures_file)?;
+                writeln!(
+                    failures,
+                    "Run {}: {}\n  Error: {}",
+                    i + 1,
+                    runs[i].join(" "),
+                    e
+                )?;
+                failed.push(i + 1);
+            }
+            (Err(e), None) => return Err(e),
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failure::format_err!(
+            "{} of {} batch runs failed: {:?}. See {}",
+            failed.len(),
+            runs.len(),
+            failed,
+            failures_file.unwrap()
+        ))
+    }
+}
+
+/// Whether a batch should clean up the remote after the run of `cfg`, before the `next` one.
+/// Without a reboot, the mount of a run would be in the way of the next one, so clean up
+/// unless the next run reuses the mount or the memcached server the run loaded.
+fn tear_down_between(cfg: &Config, next: Option<&Config>) -> bool {
+    let next = match next {
+        Some(next) => next,
+        None => return false,
+    };
+    let load_only = matches!(
+        cfg.workload,
+        Workload::Memcached {
+            load_only: true,
+            ..
+        }
+    );
+    let reuse_load = matches!(
+        next.workload,
+        Workload::Memcached {
+            reuse_load: true,
+            ..
+        }
+    );
+
+    !(load_only || reuse_load || next.reuse_mount)
+}
+
+/// Run the experiment, then fetch its results if --fetch_results was given, even if the run
+/// failed. If `tear_down` is set, the remote is cleaned up even if the run succeeded.
+fn run_and_fetch<A>(login: &Login<A>, cfg: &Config, tear_down: bool) -> Result<(), failure::Error>
+where
+    A: std::net::ToSocketAddrs + std::fmt::Display + std::fmt::Debug + Clone,
+{
+    let result = run_inner(login, cfg);
+    if result.is_err() || tear_down {
+        // Don't leave the mounts, modules, and collectors of a failed run behind
+        teardown();
+    } else {
+        TEARDOWN.lock().unwrap().take();
+    }
+
+    if let Some(local_dir) = &cfg.fetch_results {
+        if let Err(e) = fetch_results(login, cfg, local_dir) {
+            // Don't hide the error of the run itself
+            if result.is_ok() {
+                return Err(e);
+            }
+            println!("WARNING: unable to fetch the results: {}", e);
+        }
+    }
+
+    result
+}
+
+/// Copy all of the results files of a run from the remote to `local_dir` on this machine.
+fn fetch_results<A>(login: &Login<A>, cfg: &Config, local_dir: &str) -> Result<(), failure::Error>
+where
+    A: std::net::ToSocketAddrs + std::fmt::Display + std::fmt::Debug + Clone,
+{
+    let ushell = SshShell::with_any_key(login.username, &login.host)?;
+    let user_home = get_user_home_dir(&ushell)?;
+    let results_dir = dir!(&user_home, crate::RESULTS_PATH);
+
+    // Every results file of this run starts with the same prefix
+    let prefix = cfg.gen_file_name("");
+    let tarball = format!("/tmp/{}tar.gz", prefix);
+    ushell.run(
+        cmd!("tar czf {} {}*", tarball, prefix)
+            .cwd(&results_dir)
+            .use_bash(),
+    )?;
+
+    std::fs::create_dir_all(local_dir)?;
+    let local_tarball = dir!(local_dir, format!("{}tar.gz", prefix));
+    for (prog, args) in [
+        (
+            "scp",
+            vec![
+                format!("{}@{}:{}", login.username, login.hostname, tarball),
+                local_tarball.clone(),
+            ],
+        ),
+        (
+            "tar",
+            vec![
+                "xzf".into(),
+                local_tarball.clone(),
+                "-C".into(),
+                local_dir.into(),
+            ],
+        ),
+    ] {
+        let status = std::process::Command::new(prog).args(&args).status()?;
+        if !status.success() {
+            return Err(failure::format_err!(
+                "`{} {}` failed with {}",
+                prog,
+                args.join(" "),
+                status
+            ));
+        }
+    }
+
+    std::fs::remove_file(&local_tarball)?;
+    ushell.run(cmd!("rm -f {}", tarball))?;
+
+    println!("Fetched the results to {}", local_dir);
+
+    Ok(())
+}
+
+/// The columns `summarize` shows for a run, read from the run's params file: a few readable
+/// ones first, then every field of the `Config`. The params are parsed back into a `Config`, so
+/// every run has the same columns in the same order, even if its params file lacks a field.
+pub(crate) fn summary_columns(params: &str) -> Result<Vec<(String, String)>, failure::Error> {
+    let cfg: Config = serde_json::from_str(params)
+        .map_err(|e| failure::format_err!("Not a params file from fbmm_exp: {}", e))?;
+    let region = |region: &Option<MemRegion>| {
+        region.map_or_else(|| "none".into(), |r| format!("{}G@{}G", r.size, r.start))
+    };
+
+    let mut columns = vec![
+        ("workload".into(), format!("{:?}", cfg.workload)),
+        (
+            "mmfs".into(),
+            cfg.fbmm
+                .map_or_else(|| "none".into(), |fs| format!("{:?}", fs)),
+        ),
+        ("dram_region".into(), region(&cfg.dram_region)),
+        ("pmem_region".into(), region(&cfg.pmem_region)),
+    ];
+    if let serde_json::Value::Object(fields) = serde_json::to_value(&cfg)? {
+        columns.extend(fields.into_iter().map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                v => v.to_string(),
+            };
+            (format!("cfg_{}", key), value)
+        }));
+    }
+
+    Ok(columns)
+}
+
+/// The kernel command line options an experiment needs. The isolated cores depend on the
+/// machine, so they are only included if they are known.
+fn boot_options(cfg: &Config, isolated_cores: Option<&std::ops::Range<usize>>) -> Vec<String> {
+    let mut options = Vec::new();
+
+    // If we are doing an experiment where we reserve RAM, add it in
+    if let Some(dram) = &cfg.dram_region {
+        options.push(format!("memmap={}G!{}G", dram.size, dram.start));
+        if let Some(pmem) = &cfg.pmem_region {
+            options.push(format!("memmap={}G!{}G", pmem.size, pmem.start));
+        }
+    }
+
+    // If we are doing an experiment using tpp, add in the option to setup the tiering
+    // If a node has compute, it will be considered toptier, so restrict the CPUs too
+    if cfg.tpp {
+        options.push("do_tpp maxcpus=8".into());
+    }
+
+    // Reserve huge pages at boot while memory is not fragmented
+    if let Some(hugepages_gb) = &cfg.hugepages_boot {
+        // There are 512 huge pages per GB
+        let num_pages = hugepages_gb * 1024 / 2;
+        options.push(format!(
+            "default_hugepagesz=2M hugepagesz=2M hugepages={}",
+            num_pages
+        ));
+    }
+
+    if let Some(isolated_cores) = isolated_cores {
+        options.push(format!(
+            "isolcpus={0}-{1} nohz_full={0}-{1} rcu_nocbs={0}-{1}",
+            isolated_cores.start,
+            isolated_cores.end - 1
+        ));
+    }
+
+    options
+}
+
+/// The commands that replace the experiment options on the kernel command line in the grub
+/// config with `options`.
+fn grub_cmds(options: &[String]) -> Vec<String> {
+    // First, clear the memmap, tpp, isolcpus, and hugepages options from the boot options
+    let mut cmds = vec![
+        r#"sed 's/ memmap=[0-9]*[KMG]![0-9]*[KMG]//g' \
+        /etc/default/grub | sed 's/ do_tpp//g' | sed 's/ maxcpus=[0-9]*//g' | \
+        sed 's/ isolcpus=[0-9,-]*//g' | sed 's/ nohz_full=[0-9,-]*//g' | \
+        sed 's/ rcu_nocbs=[0-9,-]*//g' | sed 's/ default_hugepagesz=[0-9]*[KMG]//g' | \
+        sed 's/ hugepagesz=[0-9]*[KMG]//g' | sed 's/ hugepages=[0-9]*//g' | \
+        sudo tee /tmp/grub"#
+            .to_owned(),
+        "sudo mv /tmp/grub /etc/default/grub".to_owned(),
+    ];
+    // Then add the options for this experiment
+    if !options.is_empty() {
+        cmds.push(format!(
+            r#"sed 's/GRUB_CMDLINE_LINUX="\(.*\)"/GRUB_CMDLINE_LINUX="\1 {}"/' \
+            /etc/default/grub | sudo tee /tmp/grub"#,
+            options.join(" ")
+        ));
+        cmds.push("sudo mv /tmp/grub /etc/default/grub".to_owned());
+    }
+    // Finally, update the grub config
+    cmds.push("sudo update-grub2".to_owned());
+
+    cmds
+}
+
+/// The source `findmnt` reports for the mount of `fs` made by `mount_cmds`.
+fn mount_source<'a>(cfg: &'a Config, fs: &MMFS) -> &'a str {
+    match fs {
+        MMFS::Ext4 | MMFS::Xfs | MMFS::TieredMMFS => &cfg.fast_dev,
+        MMFS::BasicMMFS { .. } => "BasicMMFS",
+        MMFS::ContigMMFS => "ContigMMFS",
+        MMFS::BandwidthMMFS => "BandwidthMMFS",
+        MMFS::Tmpfs { .. } => "tmpfs",
+    }
+}
+
+/// The commands, run from the home directory, that create `fs` and mount it on `daxtmp/`.
+/// The kernel module of the FS must already be inserted.
+fn mount_cmds(cfg: &Config, fs: &MMFS) -> Vec<String> {
+    match fs {
+        MMFS::Ext4 => {
+            let mut cmds = vec![
+                format!("sudo mkfs.ext4 {}", &cfg.fast_dev),
+                format!("sudo tune2fs -O ^has_journal {}", &cfg.fast_dev),
+            ];
+            if !cfg.ext4_metadata {
+                cmds.push(format!("sudo tune2fs -O ^metadata_csum {}", &cfg.fast_dev));
+            }
+            cmds.push(format!("sudo mount -o dax {} daxtmp/", &cfg.fast_dev));
+            cmds
+        }
+        MMFS::Xfs => {
+            // Reflink needs the metadata checksums, and neither is needed for FBMM
+            let meta = if cfg.xfs_metadata {
+                "reflink=0"
+            } else {
+                "crc=0,reflink=0"
+            };
+            vec![
+                format!("sudo mkfs.xfs -f -m {} {}", meta, &cfg.fast_dev),
+                format!("sudo mount -o dax {} daxtmp/", &cfg.fast_dev),
+            ]
+        }
+        MMFS::BasicMMFS { num_pages } => vec![format!(
+            "sudo mount -t BasicMMFS BasicMMFS -o numpages={} daxtmp/",
+            num_pages
+        )],
+        MMFS::TieredMMFS => vec![format!(
+            "sudo mount -t TieredMMFS -o slowmem={} -o basepage={} {} daxtmp/",
+            &cfg.slow_dev, cfg.disable_thp, &cfg.fast_dev
+        )],
+        MMFS::ContigMMFS => vec!["sudo mount -t ContigMMFS ContigMMFS daxtmp/".to_owned()],
+        MMFS::Tmpfs { size_gb } => vec![format!(
+            "sudo mount -t tmpfs -o size={}G tmpfs daxtmp/",
+            size_gb
+        )],
+        MMFS::BandwidthMMFS => {
+            vec!["sudo mount -t BandwidthMMFS BandwidthMMFS daxtmp/".to_owned()]
+        }
+    }
+}
+
+/// Print what a run with this config would do without connecting to the remote.
+fn print_plan(cfg: &Config) -> Result<(), failure::Error> {
+    let (output_file, params_file, time_file, _sim_file) = cfg.gen_standard_names();
+
+    println!("Config:\n{}", serde_json::to_string_pretty(cfg)?);
+    println!(
+        "Results in ~/{} on {}:\n  {}\n  {}\n  {}",
+        crate::RESULTS_PATH,
+        cfg.host,
+        output_file,
+        params_file,
+        time_file
+    );
+
+    let reuse_load = matches!(
+        cfg.workload,
+        Workload::Memcached {
+            reuse_load: true,
+            ..
+        }
+    );
+    if cfg.assume_setup {
+        println!("Assumes the remote is already set up, so it would not be rebooted.");
+    } else if reuse_load {
+        println!("Reuses the loaded memcached server, so it would not be rebooted.");
+    } else {
+        let mut options = boot_options(cfg, None);
+        if cfg.isolcpus {
+            options.push("isolcpus/nohz_full/rcu_nocbs=<the last workload cores>".into());
+        }
+        println!("Would update the kernel command line with:");
+        for cmd in grub_cmds(&options) {
+            println!("  {}", cmd);
+        }
+    }
+    if let Some(fs) = cfg
+        .fbmm
+        .as_ref()
+        .filter(|_| !cfg.assume_setup && !reuse_load)
+    {
+        println!("Would mount {:?} on ~/daxtmp/ with:", fs);
+        if let Some((module, path)) = fs.kernel_module() {
+            println!(
+                "  sudo insmod {} (if {} is not loaded)",
+                dir!(crate::KERNEL_PATH, path),
+                module
+            );
+        }
+        for cmd in mount_cmds(cfg, fs) {
+            println!("  {}", cmd);
+        }
+    }
+    // The rest of the workload command line depends on the cores and directories found on
+    // the remote, so only the workload itself is shown
+    println!("Would run the workload {:?}", cfg.workload);
+    if let Some(local_dir) = &cfg.fetch_results {
+        println!(
+            "Would copy the results files to {} on this machine.",
+            local_dir
+        );
+    }
+
+    Ok(())
+}
+
+fn empty_func(_: &SshShell) -> Result<(), ScailError> {
+    Ok(())
+}
+
+fn run_inner<A>(login: &Login<A>, cfg: &Config) -> Result<(), failure::Error>
 where
     A: std::net::ToSocketAddrs + std::fmt::Display + std::fmt::Debug + Clone,
 {
     // Collect timers on VM
     let mut timers = vec![];
+    let metrics = cfg
+        .metrics_port
+        .map(crate::metrics::MetricsServer::start)
+        .transpose()?;
+    let set_phase = |phase: &'static str| {
+        if let Some(metrics) = &metrics {
+            metrics.set_phase(phase);
+        }
+    };
     let ushell = SshShell::with_any_key(login.username, &login.host)?;
     let user_home = get_user_home_dir(&ushell)?;
 
@@ -689,23 +2325,73 @@ where
     let mm_fault_file = dir!(&results_dir, cfg.gen_file_name("mm_fault"));
     let mmap_tracker_file = dir!(&results_dir, cfg.gen_file_name("mmap_tracker"));
     let flame_graph_file = dir!(&results_dir, cfg.gen_file_name("flamegraph.svg"));
+    let offcpu_stacks_file = "/tmp/offcpu_stacks";
+    let offcpu_flame_graph_file = dir!(&results_dir, cfg.gen_file_name("offcpu-flamegraph.svg"));
+    // perf mem and perf c2c can't both be used, so they share a perf.data
+    let perf_sample_tool = if cfg.perf_mem {
+        Some("mem")
+    } else if cfg.perf_c2c {
+        Some("c2c")
+    } else {
+        None
+    };
+    let perf_sample_record_file = "/tmp/perf_sample.data";
+    let strace_file = dir!(&results_dir, cfg.gen_file_name("strace"));
     let smaps_file = dir!(&results_dir, cfg.gen_file_name("smaps"));
+    let numa_maps_file = dir!(&results_dir, cfg.gen_file_name("numa_maps"));
+    let zoneinfo_file = dir!(&results_dir, cfg.gen_file_name("zoneinfo"));
+    let meminfo_file = dir!(&results_dir, cfg.gen_file_name("meminfo"));
+    let numastat_file = dir!(&results_dir, cfg.gen_file_name("numastat"));
+    let sched_stats_file = dir!(&results_dir, cfg.gen_file_name("sched_stats"));
+    let collector_cpuset_file = dir!(&results_dir, cfg.gen_file_name("collector_cpuset"));
+    let time_to_rss_file = dir!(&results_dir, cfg.gen_file_name("time_to_rss"));
+    let stress_ng_file = dir!(&results_dir, cfg.gen_file_name("stress_ng"));
+    let steady_runtime_file = dir!(&results_dir, cfg.gen_file_name("steady_runtime"));
     let tmmfs_stats_periodic_file = dir!(&results_dir, cfg.gen_file_name("tmmfs_stats_periodic"));
+    let tier_occupancy_file = dir!(&results_dir, cfg.gen_file_name("tier_occupancy"));
+    let tier_occupancy_raw_file = dir!(&results_dir, cfg.gen_file_name("tier_occupancy_raw"));
     let tmmfs_active_list_periodic_file =
         dir!(&results_dir, cfg.gen_file_name("tmmfs_active_list"));
     let lock_stat_file = dir!(&results_dir, cfg.gen_file_name("lock_stat"));
     let gups_file = dir!(&results_dir, cfg.gen_file_name("gups"));
+    let gups_metric_file = dir!(&results_dir, cfg.gen_file_name("gups_metric"));
     let coherence_file = dir!(&results_dir, cfg.gen_file_name("coherence"));
     let alloc_test_file = dir!(&results_dir, cfg.gen_file_name("alloctest"));
     let ycsb_file = dir!(&results_dir, cfg.gen_file_name("ycsb"));
     let runtime_file = dir!(&results_dir, cfg.gen_file_name("runtime"));
     let tieredmmfs_stats_file = dir!(&results_dir, cfg.gen_file_name("tieredmmfs_stats"));
     let vmstat_file = dir!(&results_dir, cfg.gen_file_name("vmstat"));
+    let vmstat_periodic_file = dir!(&results_dir, cfg.gen_file_name("vmstat_periodic"));
+    let fraginfo_file = dir!(&results_dir, cfg.gen_file_name("fraginfo"));
+    let rapl_file = dir!(&results_dir, cfg.gen_file_name("rapl"));
+    let mbm_file = dir!(&results_dir, cfg.gen_file_name("mbm"));
+    let damon_file = dir!(&results_dir, cfg.gen_file_name("damon"));
+    let ftrace_file = dir!(&results_dir, cfg.gen_file_name("ftrace"));
     let graph500_file = dir!(&results_dir, cfg.gen_file_name("graph500"));
+    let rocksdb_file = dir!(&results_dir, cfg.gen_file_name("rocksdb"));
+    let gap_file = dir!(&results_dir, cfg.gen_file_name("gap"));
+    let cachebench_file = dir!(&results_dir, cfg.gen_file_name("cachebench"));
+    let multichase_file = dir!(&results_dir, cfg.gen_file_name("multichase"));
+    let xsbench_file = dir!(&results_dir, cfg.gen_file_name("xsbench"));
+    let custom_file = dir!(&results_dir, cfg.gen_file_name("custom"));
+    let spec_copies_file = dir!(&results_dir, cfg.gen_file_name("spec_copies"));
+    let gap_trials_file = dir!(&results_dir, cfg.gen_file_name("gap_trials"));
     let stream_file = dir!(&results_dir, cfg.gen_file_name("stream"));
+    let file_read_file = dir!(&results_dir, cfg.gen_file_name("file_read"));
     let badger_trap_file = dir!(&results_dir, cfg.gen_file_name("badger_trap"));
     let fbmm_stats_file = dir!(&results_dir, cfg.gen_file_name("fbmm_stats"));
+    let fbmm_stats_before_file = dir!(&results_dir, cfg.gen_file_name("fbmm_stats_before"));
     let damo_status_file = dir!(&results_dir, cfg.gen_file_name("damo_status"));
+    let cgroup_file = dir!(&results_dir, cfg.gen_file_name("cgroup"));
+    let mount_file = dir!(&results_dir, cfg.gen_file_name("mount"));
+    let dmesg_warn_file = dir!(&results_dir, cfg.gen_file_name("dmesg_warn"));
+    let dmesg_file = dir!(&results_dir, cfg.gen_file_name("dmesg"));
+    let pmem_namespaces_file = dir!(&results_dir, cfg.gen_file_name("pmem_namespaces"));
+    let reservation_check_file = dir!(&results_dir, cfg.gen_file_name("reservation_check"));
+    let cpu_features_file = dir!(&results_dir, cfg.gen_file_name("cpu_features"));
+    let force_migrate_file = dir!(&results_dir, cfg.gen_file_name("force_migrate"));
+    let before_file = dir!(&results_dir, cfg.gen_file_name("before"));
+    let after_file = dir!(&results_dir, cfg.gen_file_name("after"));
 
     let bmks_dir = dir!(&user_home, crate::RESEARCH_WORKSPACE_PATH, crate::BMKS_PATH);
     let gups_dir = dir!(&bmks_dir, "gups/");
@@ -722,79 +2408,282 @@ where
     let spec_dir = dir!(&bmks_dir, crate::SPEC2017_PATH);
     let hmsdk_dir = dir!(&user_home, "hmsdk");
     let parsec_dir = dir!(&user_home, crate::PARSEC_PATH);
+    let gap_dir = dir!(&user_home, crate::GAP_PATH);
     let postgres_db_dir = dir!(&user_home, "pgtmp");
+    let redis_conf = "/tmp/redis.conf";
+    let rocksdb_dir = dir!(&bmks_dir, "rocksdb");
+    let xsbench_dir = dir!(&bmks_dir, "XSBench/openmp-threading");
 
-    // Setup the pmem settings in the grub config before rebooting
-    // First, clear the memmap and tpp options from the boot options
-    ushell.run(cmd!("cat /etc/default/grub"))?;
-    ushell.run(cmd!(
-        r#"sed 's/ memmap=[0-9]*[KMG]![0-9]*[KMG]//g' \
-        /etc/default/grub | sed 's/ do_tpp//g' | sed 's/ maxcpus=[0-9]*//g' | \
-        sudo tee /tmp/grub"#
-    ))?;
-    ushell.run(cmd!("sudo mv /tmp/grub /etc/default/grub"))?;
-    // Then, if we are doing an experiment where we reserve RAM, add it in
-    if let Some(dram) = &cfg.dram_region {
-        if let Some(pmem) = &cfg.pmem_region {
+    // Figure out how many cores the workload will use
+    let num_pin_cores = match &cfg.workload {
+        // Each rate copy gets its own core
+        w if w.spec_benchmark(false).is_some() && cfg.spec_rate.is_some() => cfg.spec_rate.unwrap(),
+        Workload::Spec2017CactuBSSN => 16,
+        w if w.spec_benchmark(false).is_some() => 4,
+        Workload::Gups { threads, .. }
+        | Workload::AllocTest { threads, .. }
+        | Workload::Canneal { threads, .. }
+        | Workload::FileRead { threads, .. }
+        | Workload::Gap { threads, .. }
+        | Workload::Custom { threads, .. }
+        | Workload::Xsbench { threads, .. }
+        | Workload::Stream { threads } => *threads,
+        _ => 1,
+    };
+
+    // If we are isolating the workload cores, use the last cores so the choice is deterministic
+    let isolated_cores = if cfg.isolcpus {
+        let num_cores = libscail::get_num_cores(&ushell)?;
+        if num_pin_cores > num_cores {
+            return Err(failure::format_err!(
+                "Cannot isolate {} cores on a machine with {} cores",
+                num_pin_cores,
+                num_cores
+            ));
+        }
+        let first = num_cores - num_pin_cores;
+
+        Some(first..num_cores)
+    } else {
+        None
+    };
+
+    // Without a reboot, the grub edits won't apply, so it has to be booted as we need already
+    if cfg.no_reboot {
+        check_booted_options(&ushell, cfg, isolated_cores.as_ref())?;
+    }
+
+    // Reusing a previous load means the loaded memcached server must still be running,
+    // so we can't reboot
+    let reuse_load = matches!(
+        cfg.workload,
+        Workload::Memcached {
+            reuse_load: true,
+            ..
+        }
+    );
+    if reuse_load {
+        let stats = ushell.run(cmd!(
+            "{}/scripts/memcached-tool localhost:11211 stats",
+            memcached_dir
+        ));
+        if stats.is_err() {
+            return Err(failure::format_err!(
+                "--reuse_load needs the memcached server of a previous --load_only run, but \
+                 memcached is not running on the remote"
+            ));
+        }
+    }
+
+    // With --assume_setup, the host is already booted with the right options. With
+    // --reuse_load, it won't be rebooted, so leave the grub config for the next boot alone.
+    if !cfg.assume_setup && !reuse_load {
+        // Setup the pmem settings in the grub config before rebooting
+        ushell.run(cmd!("cat /etc/default/grub"))?;
+        for cmd in grub_cmds(&boot_options(cfg, isolated_cores.as_ref())) {
+            ushell.run(cmd!("{}", cmd))?;
+        }
+
+        // Let the workload lock all of its memory. This applies to the sessions after the reboot.
+        if cfg.mlock {
             ushell.run(cmd!(
-                r#"sed 's/GRUB_CMDLINE_LINUX="\(.*\)"/GRUB_CMDLINE_LINUX="\1 memmap={}G!{}G memmap={}G!{}G"/' \
-                /etc/default/grub | sudo tee /tmp/grub"#,
-                dram.size, dram.start, pmem.size, pmem.start
+                "echo '* - memlock unlimited' | sudo tee /etc/security/limits.d/99-mlock.conf"
             ))?;
-            ushell.run(cmd!("sudo mv /tmp/grub /etc/default/grub"))?;
         } else {
-            ushell.run(cmd!(
-                r#"sed 's/GRUB_CMDLINE_LINUX="\(.*\)"/GRUB_CMDLINE_LINUX="\1 memmap={}G!{}G"/' \
-                /etc/default/grub | sudo tee /tmp/grub"#,
-                dram.size,
-                dram.start
-            ))?;
-            ushell.run(cmd!("sudo mv /tmp/grub /etc/default/grub"))?;
+            ushell.run(cmd!("sudo rm -f /etc/security/limits.d/99-mlock.conf"))?;
         }
     }
-    // If we are doing an experiment using tpp, add in the option to setup the tiering
-    // If a node has compute, it will be considered toptier, so restrict the CPUs too
-    if cfg.tpp {
-        ushell.run(cmd!(
-            r#"sed 's/GRUB_CMDLINE_LINUX="\(.*\)"/GRUB_CMDLINE_LINUX="\1 do_tpp maxcpus=8"/' \
-            /etc/default/grub | sudo tee /tmp/grub"#
-        ))?;
-        ushell.run(cmd!("sudo mv /tmp/grub /etc/default/grub"))?;
-    }
 
-    // Finally, update the grub config
-    ushell.run(cmd!("sudo update-grub2"))?;
+    set_phase("reboot");
+    let ushell = if reuse_load || cfg.assume_setup {
+        ushell
+    } else {
+        connect_and_setup_host(
+            login,
+            !cfg.no_reboot,
+            cfg.power_cycle_cmd.as_deref(),
+            std::time::Duration::from_secs(cfg.power_cycle_timeout),
+            &RebootLimits {
+                initial_sleep: std::time::Duration::from_secs(cfg.reboot_initial_sleep),
+                timeout: cfg.reboot_timeout.map(std::time::Duration::from_secs),
+                retries: cfg.reboot_retries,
+            },
+        )?
+    };
+
+    // If the run fails or the runner is interrupted from here on, stop what the run started
+    // on the remote
+    let mut teardown_cmds = vec![
+        "sudo killall -SIGINT mm_fault_tracker.py".to_owned(),
+        "sudo killall -SIGINT mmap_tracker.py".to_owned(),
+    ];
+    if cfg.offcpu_flame_graph {
+        teardown_cmds.push("sudo pkill -INT -f '[o]ffcputime-bpfcc'".into());
+    }
+    if cfg.damon.is_some() {
+        teardown_cmds
+            .push("echo off | sudo tee /sys/kernel/mm/damon/admin/kdamonds/0/state".into());
+    }
+    if cfg.ftrace.is_some() {
+        teardown_cmds.push("echo 0 | sudo tee /sys/kernel/debug/tracing/tracing_on".into());
+    }
+    match cfg.workload {
+        Workload::Memcached { .. } => teardown_cmds.push("sudo pkill -INT memcached".into()),
+        Workload::Redis { .. } => teardown_cmds.push("sudo pkill -INT redis-server".into()),
+        Workload::Postgres { .. } => teardown_cmds.push("sudo pkill -INT postgres".into()),
+        _ => {}
+    }
+    if cfg.stress_ng_vm.is_some() {
+        teardown_cmds.push("killall -SIGINT stress-ng".into());
+    }
+    if let Some(fs) = &cfg.fbmm {
+        teardown_cmds.push(format!("echo 0 | sudo tee {}", FBMM_STATE));
+        teardown_cmds.push(format!("sudo umount {}/daxtmp/", user_home));
+        if let Some((module, _)) = fs.kernel_module() {
+            teardown_cmds.push(format!("sudo rmmod {}", module));
+        }
+    }
+    set_teardown(login, teardown_cmds);
 
-    let ushell = connect_and_setup_host(login)?;
+    // Only keep the kernel messages from this run
+    ushell.run(cmd!("sudo dmesg -C"))?;
 
-    if let Some(hugetlb_size_gb) = &cfg.hugetlb {
+    if let Some(hugetlb_size_gb) = cfg.hugetlb.filter(|_| !cfg.assume_setup) {
         // There are 512 huge pages per GB
         let num_pages = hugetlb_size_gb * 1024 / 2;
         ushell.run(cmd!("sudo hugeadm --pool-pages-min 2MB:{}", num_pages))?;
         // Print out the huge page reservations for the log
         ushell.run(cmd!("hugeadm --pool-list"))?;
     }
+    if cfg.hugepages_boot.is_some() {
+        // Print out the huge page reservations from boot for the log
+        ushell.run(cmd!("hugeadm --pool-list"))?;
+    }
 
+    // Save what the CPU looks like, since the same config can behave differently across
+    // machine types
+    ushell.run(cmd!(
+        "(lscpu; echo; getconf -a | grep CACHE; echo; cat /proc/cpuinfo) > {}",
+        &cpu_features_file
+    ))?;
+
+    // The kernel can silently reserve something other than what memmap asked for
+    if cfg.dram_region.is_some() || cfg.pmem_region.is_some() {
+        check_reservations(&ushell, cfg, &reservation_check_file)?;
+    }
+
+    if !cfg.node_weights.is_empty() {
+        let nodes = get_numa_nodes(&ushell)?;
+        if let Some(weight) = cfg.node_weights.iter().find(|w| !nodes.contains(&w.nid)) {
+            return Err(failure::format_err!(
+                "--node_weight is given for node {}, but the remote only has nodes {:?}",
+                weight.nid,
+                nodes
+            ));
+        }
+    }
+
+    let mut params = cfg.clone();
+    params.remote_research_settings = libscail::get_remote_research_settings(&ushell)?;
     ushell.run(cmd!(
         "echo {} > {}",
-        escape_for_bash(&serde_json::to_string(&cfg)?),
+        escape_for_bash(&serde_json::to_string(&params)?),
         dir!(&results_dir, params_file)
     ))?;
 
+    // Make sure the workload was actually built so a missing binary isn't mistaken for the
+    // workload crashing
+    let mut workload_bins = match &cfg.workload {
+        Workload::AllocTest { .. } => vec![dir!(&bmks_dir, "alloc_test")],
+        Workload::Canneal { .. } => vec![dir!(&parsec_dir, "bin/parsecmgmt")],
+        Workload::Gups {
+            variant: GupsVariant::Basic,
+            ..
+        } => vec![dir!(&gups_dir, "gups")],
+        Workload::Gups {
+            variant: GupsVariant::Hotset,
+            ..
+        } => vec![dir!(&gups_dir, "gups-hotset-move")],
+        Workload::Memcached { .. } => vec![
+            dir!(&memcached_dir, "memcached"),
+            dir!(&ycsb_dir, "bin/ycsb"),
+        ],
+        Workload::Postgres { .. } => {
+            vec![dir!(postgres_dir, "postgres"), dir!(&ycsb_dir, "bin/ycsb")]
+        }
+        // redis is installed from the distro by setup_wkspc
+        Workload::Redis { .. } => vec!["/usr/bin/redis-server".into(), dir!(&ycsb_dir, "bin/ycsb")],
+        Workload::Graph500 { .. } => vec![dir!(&graph500_dir, "graph500_reference_bfs_sssp")],
+        Workload::Rocksdb { .. } => vec![dir!(&rocksdb_dir, "db_bench")],
+        Workload::Stream { .. } => vec![dir!(&bmks_dir, "stream")],
+        Workload::FileRead { .. } => vec![dir!(&bmks_dir, "file_read")],
+        Workload::Gap { kernel, .. } => {
+            vec![dir!(&gap_dir, "converter"), dir!(&gap_dir, kernel.binary())]
+        }
+        Workload::CacheBench { .. } => vec![dir!(&bmks_dir, "cachebench")],
+        Workload::Multichase { .. } => vec![dir!(&bmks_dir, "multichase/multichase")],
+        Workload::Xsbench { .. } => vec![dir!(&xsbench_dir, "XSBench")],
+        Workload::Idle { .. } => vec![],
+        Workload::Custom { binary, .. } => vec![binary.clone()],
+        // SPEC is installed separately, and pagewalk_coherence is built right before it runs
+        Workload::Spec2017Mcf
+        | Workload::Spec2017Xalancbmk
+        | Workload::Spec2017Xz { .. }
+        | Workload::Spec2017CactuBSSN
+        | Workload::Spec2017Lbm
+        | Workload::Spec2017Bwaves
+        | Workload::Spec2017Roms
+        | Workload::Spec2017Fotonik3d
+        | Workload::Spec2017Omnetpp
+        | Workload::PagewalkCoherence { .. } => vec![],
+    };
+    if cfg.mlock {
+        workload_bins.push(dir!(&bmks_dir, "mlockall.so"));
+    }
+    for bin in &workload_bins {
+        ushell.run(cmd!("test -x {}", bin)).map_err(|_| {
+            failure::format_err!(
+                "The workload binary {} does not exist. Did you run `setup_wkspc --host_bmks`?",
+                bin
+            )
+        })?;
+    }
+
     let mut cmd_prefix = String::new();
     let proc_name = match &cfg.workload {
         Workload::AllocTest { .. } => "alloc_test",
-        Workload::Canneal { workload: _ } => "canneal",
-        Workload::Spec2017Mcf => "mcf_s",
-        Workload::Spec2017Xalancbmk => "xalancbmk_s",
-        Workload::Spec2017Xz { size: _ } => "xz_s",
-        Workload::Spec2017CactuBSSN => "cactuBSSN_s",
+        Workload::Canneal { .. } => "canneal",
+        // The binary is the benchmark name without the number, e.g. mcf_s for 605.mcf_s
+        Workload::Spec2017Mcf
+        | Workload::Spec2017Xalancbmk
+        | Workload::Spec2017Xz { .. }
+        | Workload::Spec2017CactuBSSN
+        | Workload::Spec2017Lbm
+        | Workload::Spec2017Bwaves
+        | Workload::Spec2017Roms
+        | Workload::Spec2017Fotonik3d
+        | Workload::Spec2017Omnetpp => {
+            let benchmark = cfg
+                .workload
+                .spec_benchmark(cfg.spec_rate.is_some())
+                .unwrap();
+            benchmark.split_once('.').unwrap().1
+        }
         Workload::Gups { .. } => "gups",
         Workload::PagewalkCoherence { .. } => "paging",
         Workload::Memcached { .. } => "memcached",
         Workload::Postgres { .. } => "postgres",
+        Workload::Redis { .. } => "redis-server",
         Workload::Graph500 { .. } => "graph500_refere",
+        Workload::Rocksdb { .. } => "db_bench",
         Workload::Stream { .. } => "stream",
+        Workload::FileRead { .. } => "file_read",
+        Workload::Gap { kernel, .. } => kernel.binary(),
+        Workload::CacheBench { .. } => "cachebench",
+        Workload::Multichase { .. } => "multichase",
+        Workload::Xsbench { .. } => "XSBench",
+        Workload::Idle { .. } => "sleep",
+        Workload::Custom { proc_name, .. } => proc_name.as_str(),
     };
 
     let (
@@ -811,21 +2700,31 @@ where
         transparent_hugepage_enabled,
         transparent_hugepage_defrag,
         transparent_hugepage_khugepaged_defrag,
-        1000,
-        1000,
+        cfg.khugepaged_alloc_sleep.unwrap_or(1000),
+        cfg.khugepaged_scan_sleep.unwrap_or(1000),
     )?;
+    if let Some(max_ptes_none) = cfg.khugepaged_max_ptes_none {
+        ushell.run(cmd!(
+            "echo {} | sudo tee /sys/kernel/mm/transparent_hugepage/khugepaged/max_ptes_none",
+            max_ptes_none
+        ))?;
+    }
 
     if cfg.disable_aslr {
         libscail::disable_aslr(&ushell)?;
-    } else {
+    } else if !cfg.per_proc_no_aslr {
         libscail::enable_aslr(&ushell)?;
     }
 
     let mut tctx = match &cfg.workload {
         Workload::Memcached { .. }
+        | Workload::Redis { .. }
         | Workload::Postgres { .. }
         | Workload::Gups { .. }
-        | Workload::Stream { .. } => TasksetCtxBuilder::from_lscpu(&ushell)?
+        | Workload::Stream { .. }
+        | Workload::FileRead { .. }
+        | Workload::Xsbench { .. }
+        | Workload::Gap { .. } => TasksetCtxBuilder::from_lscpu(&ushell)?
             .numa_interleaving(TasksetCtxInterleaving::Sequential)
             .skip_hyperthreads(true)
             .build(),
@@ -842,20 +2741,26 @@ where
     };
 
     // Figure out which cores we will use for the workload
-    let num_pin_cores = match &cfg.workload {
-        Workload::Spec2017Mcf | Workload::Spec2017Xz { .. } | Workload::Spec2017Xalancbmk => 4,
-        Workload::Spec2017CactuBSSN => 16,
-        Workload::Gups { threads, .. }
-        | Workload::AllocTest { threads, .. }
-        | Workload::Stream { threads } => *threads,
-        _ => 1,
-    };
     let mut pin_cores = Vec::<usize>::new();
-    for _ in 0..num_pin_cores {
-        if let Ok(new_core) = tctx.next() {
-            pin_cores.push(new_core);
-        } else {
-            return Err(std::fmt::Error.into());
+    let node_cores = if cfg.instances_per_node {
+        Some(get_node_cores(&ushell, num_pin_cores)?)
+    } else {
+        None
+    };
+    if let Some(isolated_cores) = isolated_cores {
+        pin_cores.extend(isolated_cores);
+    } else if let Some(node_cores) = &node_cores {
+        // Each instance gets its own cores, but the collectors should see all of them
+        for (_, cores) in node_cores {
+            pin_cores.extend(cores);
+        }
+    } else {
+        for _ in 0..num_pin_cores {
+            if let Ok(new_core) = tctx.next() {
+                pin_cores.push(new_core);
+            } else {
+                return Err(std::fmt::Error.into());
+            }
         }
     }
 
@@ -864,66 +2769,426 @@ where
         .map(ToString::to_string)
         .collect::<Vec<_>>()
         .join(",");
-    if cfg.perf_stat {
-        let mut extra_args = format!(" -C {} ", &pin_cores_str);
 
-        if cfg.perf_periodic {
-            // Times 1000 because PERIOD is in seconds, and -I takes ms
-            extra_args.push_str(format!(" -I {} ", PERIOD * 1000).as_str());
+    // The YCSB client gets its own cores after the server's
+    let ycsb_client_cores: Vec<usize> = match cfg.workload {
+        Workload::Memcached { run_threads, .. } => (0..run_threads.unwrap_or(1))
+            .map_while(|_| tctx.next().ok())
+            .collect(),
+        Workload::Redis { .. } => tctx.next().into_iter().collect(),
+        _ => vec![],
+    };
+
+    // The stressors go on the next cores so they don't compete with the workload for CPU
+    let stress_ng_cores: Vec<usize> = (0..cfg.stress_ng_vm.unwrap_or(0))
+        .map_while(|_| tctx.next().ok())
+        .collect();
+    if let Some(workers) = cfg.stress_ng_vm {
+        if stress_ng_cores.len() < workers {
+            return Err(failure::format_err!(
+                "Not enough cores left for {} stress-ng workers",
+                workers
+            ));
+        }
+    }
+
+    // The collectors get whatever cores the workload and its clients don't use
+    let collector_cores = if cfg.reserve_cpuset {
+        let num_cores = libscail::get_num_cores(&ushell)?;
+        let cores = (0..num_cores)
+            .filter(|c| {
+                !pin_cores.contains(c)
+                    && !ycsb_client_cores.contains(c)
+                    && !stress_ng_cores.contains(c)
+            })
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        if cores.is_empty() {
+            return Err(failure::format_err!(
+                "No cores are left for the collectors with --reserve_cpuset"
+            ));
+        }
+        let cores = cores.join(",");
+        ushell.run(cmd!(
+            "(echo workload: {}; echo collectors: {}) > {}",
+            &pin_cores_str,
+            &cores,
+            &collector_cpuset_file
+        ))?;
+        Some(cores)
+    } else {
+        None
+    };
+    // With --wait_rss_gb, the collectors only create their output until the RSS is reached
+    let rss_reached_file = "/tmp/rss_reached";
+    let rss_wait_stop_file = "/tmp/rss_wait_stop";
+    if cfg.wait_rss_gb.is_some() {
+        ushell.run(cmd!("rm -f {} {}", rss_reached_file, rss_wait_stop_file))?;
+    }
+    let collector_cmd = |cmd: String, output: &str| -> String {
+        let cmd = if cfg.wait_rss_gb.is_some() {
+            format!(
+                "if [ -e {} ]; then {}; else touch {}; fi",
+                rss_reached_file, cmd, output
+            )
+        } else {
+            cmd
+        };
+        if let Some(cores) = &collector_cores {
+            format!("taskset -c {} bash -c {}", cores, escape_for_bash(&cmd))
+        } else {
+            cmd
+        }
+    };
+
+    if cfg.perf_stat {
+        let mut extra_args = format!(" -C {} ", &pin_cores_str);
+
+        if cfg.perf_periodic {
+            // Times 1000 because the period is in seconds, and -I takes ms
+            extra_args.push_str(format!(" -I {} ", cfg.sample_period * 1000).as_str());
+        }
+
+        // perf stat prints the derived metrics itself as long as it has the counters for them
+        let mut perf_counters = cfg.perf_counters.clone();
+        if cfg.perf_stat_derived {
+            const DERIVED_COUNTERS: &[&str] = &[
+                "cycles",
+                "instructions",
+                "branches",
+                "branch-misses",
+                "cache-references",
+                "cache-misses",
+            ];
+            for counter in DERIVED_COUNTERS {
+                if !perf_counters.iter().any(|c| c == counter) {
+                    perf_counters.push((*counter).into());
+                }
+            }
         }
 
         cmd_prefix.push_str(&gen_perf_command_prefix(
             perf_stat_file,
-            &cfg.perf_counters,
+            &perf_counters,
             extra_args,
         ));
     }
 
+    // The software events don't need root, and counting only the workload (and its children)
+    // means a migration really was the workload moving between cores
+    let sched_perf_file = "/tmp/sched_perf_stat";
+    let sched_status_file = "/tmp/sched_status";
+    if cfg.sched_stats {
+        cmd_prefix.push_str(&format!(
+            "perf stat -e context-switches,cpu-migrations -o {} ",
+            sched_perf_file
+        ));
+    }
+
     if cfg.flame_graph {
+        let switch_output = if let Some(switch) = &cfg.perf_switch_output {
+            // Don't mix in the segments from a previous run
+            ushell.run(cmd!("sudo rm -f {}.*", &perf_record_file))?;
+            format!("--switch-output={} ", switch)
+        } else {
+            String::new()
+        };
+        cmd_prefix.push_str(&format!(
+            "sudo perf record -a -C {} -g -F 1999 {}-o {} ",
+            &pin_cores_str, switch_output, &perf_record_file
+        ));
+    }
+
+    if let Some(tool) = perf_sample_tool {
         cmd_prefix.push_str(&format!(
-            "sudo perf record -a -C {} -g -F 1999 -o {} ",
-            &pin_cores_str, &perf_record_file
+            "sudo perf {} record -a -C {} -o {} ",
+            tool, &pin_cores_str, perf_sample_record_file
         ));
     }
 
+    // Don't let the collectors follow the workload of a previous run
+    ushell.run(cmd!("sudo rm -f {}", WORKLOAD_PID_FILE))?;
+
     let mut bgctx = BackgroundContext::new(&ushell);
     if cfg.smaps_periodic {
         bgctx.spawn(BackgroundTask {
             name: "smaps",
-            period: PERIOD,
-            cmd: format!(
-                "((sudo cat /proc/`pgrep -x {}  | sort -n \
-                    | head -n1`/smaps) || echo none) | tee -a {}",
-                &proc_name, &smaps_file
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "((sudo cat /proc/{}/smaps) || echo none) | tee -a {}",
+                    workload_pid_cmd(&proc_name),
+                    &smaps_file
+                ),
+                &smaps_file,
             ),
             ensure_started: smaps_file,
         })?;
     }
 
+    if let Some(at) = cfg.numa_maps_snapshot {
+        // Only keep one sample: the one from when the workload's RSS was the largest, or the
+        // latest one, since the process is gone by the time the workload finishes
+        let max_rss_file = "/tmp/numa_maps_max_rss";
+        let keep_sample = match at {
+            NumaMapsAt::PeakRss => {
+                ushell.run(cmd!("echo 0 > {}", max_rss_file))?;
+                format!(
+                    "rss=`ps -o rss= -p $pid 2>/dev/null || echo 0`; \
+                    [ \"$rss\" -gt `cat {0}` ] && echo $rss > {0}",
+                    max_rss_file
+                )
+            }
+            NumaMapsAt::End => "true".into(),
+        };
+        bgctx.spawn(BackgroundTask {
+            name: "numa_maps",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "touch {0}; pid={1}; \
+                if {2}; then \
+                    (sudo cat /proc/$pid/numa_maps > {0}.new && mv {0}.new {0}) || true; \
+                fi",
+                    &numa_maps_file,
+                    workload_pid_cmd(&proc_name),
+                    keep_sample
+                ),
+                &numa_maps_file,
+            ),
+            ensure_started: numa_maps_file,
+        })?;
+    }
+
+    if cfg.meminfo_periodic {
+        bgctx.spawn(BackgroundTask {
+            name: "meminfo",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "(echo \"== `date +%s` ==\"; cat /proc/meminfo) | tee -a {}",
+                    &meminfo_file
+                ),
+                &meminfo_file,
+            ),
+            ensure_started: meminfo_file,
+        })?;
+    }
+
+    if cfg.numastat_periodic {
+        bgctx.spawn(BackgroundTask {
+            name: "numastat",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "(echo \"== `date +%s` ==\"; numastat -m) | tee -a {}",
+                    &numastat_file
+                ),
+                &numastat_file,
+            ),
+            ensure_started: numastat_file,
+        })?;
+    }
+
+    if cfg.vmstat_periodic {
+        bgctx.spawn(BackgroundTask {
+            name: "vmstat",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "(echo \"== `date +%s` ==\"; cat /proc/vmstat) | tee -a {}",
+                    &vmstat_periodic_file
+                ),
+                &vmstat_periodic_file,
+            ),
+            ensure_started: vmstat_periodic_file,
+        })?;
+    }
+
+    if cfg.fraginfo_periodic {
+        bgctx.spawn(BackgroundTask {
+            name: "fraginfo",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "(echo \"== `date +%s` ==\"; cat /proc/buddyinfo; \
+                    sudo cat /proc/pagetypeinfo) | tee -a {}",
+                    &fraginfo_file
+                ),
+                &fraginfo_file,
+            ),
+            ensure_started: fraginfo_file,
+        })?;
+    }
+
+    let kdamond = "/sys/kernel/mm/damon/admin/kdamonds/0";
+    if let Some((sample_us, aggr_us)) = cfg.damon {
+        if ushell
+            .run(cmd!("test -d /sys/kernel/mm/damon/admin"))
+            .is_err()
+        {
+            return Err(failure::format_err!(
+                "--damon needs the DAMON sysfs interface, which this kernel doesn't have"
+            ));
+        }
+
+        // Monitor one process, and use a "stat" scheme that matches every region so the
+        // regions and their access frequencies can be read back
+        let ctx = dir!(kdamond, "contexts/0");
+        for (file, value) in [
+            (
+                "/sys/kernel/mm/damon/admin/kdamonds/nr_kdamonds".to_owned(),
+                "1".to_owned(),
+            ),
+            (dir!(kdamond, "contexts/nr_contexts"), "1".into()),
+            (dir!(&ctx, "operations"), "vaddr".into()),
+            (
+                dir!(&ctx, "monitoring_attrs/intervals/sample_us"),
+                sample_us.to_string(),
+            ),
+            (
+                dir!(&ctx, "monitoring_attrs/intervals/aggr_us"),
+                aggr_us.to_string(),
+            ),
+            (dir!(&ctx, "targets/nr_targets"), "1".into()),
+            (dir!(&ctx, "schemes/nr_schemes"), "1".into()),
+            (dir!(&ctx, "schemes/0/action"), "stat".into()),
+        ] {
+            ushell.run(cmd!("echo {} | sudo tee {}", value, file))?;
+        }
+
+        // The workload doesn't exist yet, so start monitoring once it does
+        bgctx.spawn(BackgroundTask {
+            name: "damon",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "k={0}; \
+                    if [ `cat $k/state` = off ]; then \
+                        echo {1} | sudo tee $k/contexts/0/targets/0/pid_target && \
+                        echo on | sudo tee $k/state; \
+                    fi > /dev/null 2>&1; \
+                    if [ `cat $k/state` = on ]; then \
+                        echo update_schemes_tried_regions | sudo tee $k/state > /dev/null; \
+                        echo \"== `date +%s` ==\"; \
+                        for r in $k/contexts/0/schemes/0/tried_regions/[0-9]*; do \
+                            echo `cat $r/start $r/end $r/nr_accesses $r/age`; \
+                        done; \
+                    fi | tee -a {2}",
+                    kdamond,
+                    workload_pid_cmd(&proc_name),
+                    &damon_file
+                ),
+                &damon_file,
+            ),
+            ensure_started: damon_file,
+        })?;
+    }
+
+    if cfg.sched_stats {
+        // The process is gone by the time the workload finishes, so keep the latest sample
+        bgctx.spawn(BackgroundTask {
+            name: "sched_status",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "touch {0}; pid={1}; \
+                (grep ctxt_switches /proc/$pid/status > {0}.new && mv {0}.new {0}) || true",
+                    sched_status_file,
+                    workload_pid_cmd(&proc_name)
+                ),
+                sched_status_file,
+            ),
+            ensure_started: sched_status_file.into(),
+        })?;
+    }
+
     if cfg.tmmfs_stats_periodic {
         bgctx.spawn(BackgroundTask {
             name: "tieredmmfs_stats",
-            period: PERIOD,
-            cmd: format!(
-                "(cat /sys/fs/tieredmmfs/stats || echo wait) | tee -a {}",
-                &tmmfs_stats_periodic_file
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "(cat /sys/fs/tieredmmfs/stats || echo wait) | tee -a {}",
+                    &tmmfs_stats_periodic_file
+                ),
+                &tmmfs_stats_periodic_file,
             ),
             ensure_started: tmmfs_stats_periodic_file,
         })?;
     }
 
+    if cfg.tier_occupancy_periodic {
+        // Keep the whole stats file for each sample; it is turned into per-tier page counts
+        // once the workload is done.
+        bgctx.spawn(BackgroundTask {
+            name: "tier_occupancy",
+            period: cfg.sample_period,
+            cmd: collector_cmd(
+                format!(
+                    "(echo \"== `date +%s`\" && cat /sys/fs/tieredmmfs/stats 2>/dev/null) >> {}",
+                    &tier_occupancy_raw_file
+                ),
+                &tier_occupancy_raw_file,
+            ),
+            ensure_started: tier_occupancy_raw_file.clone(),
+        })?;
+    }
+
+    let active_list_truncated_file = "/tmp/active_list_truncated";
     if cfg.tmmfs_active_list_periodic {
+        let collect = format!(
+            "(cat /sys/fs/tieredmmfs/active_list || echo wait) | tee -a {}",
+            &tmmfs_active_list_periodic_file
+        );
+        let cmd = if let Some(max_mb) = cfg.active_list_max_mb {
+            // Once the output is too big, leave a note in it and stop collecting
+            ushell.run(cmd!("rm -f {}", active_list_truncated_file))?;
+            format!(
+                "touch {0}; if [ `stat -c %s {0}` -lt {1} ]; then {2}; \
+                elif [ ! -e {3} ]; then \
+                    echo 'TRUNCATED: reached --active_list_max_mb {4}' | tee -a {0}; \
+                    touch {3}; \
+                fi",
+                &tmmfs_active_list_periodic_file,
+                max_mb << 20,
+                collect,
+                active_list_truncated_file,
+                max_mb
+            )
+        } else {
+            collect
+        };
         bgctx.spawn(BackgroundTask {
             name: "tieredmmfs_active_list",
-            period: PERIOD * 3, // This is a lot of data, so *3 to limit collection
-            cmd: format!(
-                "(cat /sys/fs/tieredmmfs/active_list || echo wait) | tee -a {}",
-                &tmmfs_active_list_periodic_file
-            ),
+            period: cfg.sample_period * 3, // This is a lot of data, so *3 to limit collection
+            cmd: collector_cmd(cmd, &tmmfs_active_list_periodic_file),
             ensure_started: tmmfs_active_list_periodic_file,
         })?;
     }
 
+    if let Some(period) = cfg.force_migrate_period {
+        let trigger = if let Some(MMFS::TieredMMFS) = cfg.fbmm {
+            "/sys/fs/tieredmmfs/migrate_now"
+        } else {
+            "/proc/sys/vm/compact_memory"
+        };
+        // Record when each migration was forced so it can be lined up with the other
+        // periodic collectors
+        bgctx.spawn(BackgroundTask {
+            name: "force_migrate",
+            period,
+            cmd: collector_cmd(
+                format!(
+                    "(echo 1 | sudo tee {} > /dev/null; date +%s) | tee -a {}",
+                    trigger, &force_migrate_file
+                ),
+                &force_migrate_file,
+            ),
+            ensure_started: force_migrate_file,
+        })?;
+    }
+
     if cfg.numactl {
         cmd_prefix.push_str("numactl --membind=0 ");
     }
@@ -954,6 +3219,23 @@ where
         cmd_prefix.push_str("sudo cgexec -g memory:hmsdk ");
     }
 
+    // Limit the workload's memory with its own cgroup, recreated so its events start at 0
+    let mem_cgroup = "/sys/fs/cgroup/fbmm_exp";
+    if cfg.mem_high.is_some() || cfg.mem_max.is_some() {
+        ushell.run(cmd!("(sudo rmdir {0} || true) && sudo mkdir {0}", mem_cgroup).use_bash())?;
+        for (file, gb) in [("memory.high", cfg.mem_high), ("memory.max", cfg.mem_max)] {
+            if let Some(gb) = gb {
+                ushell.run(cmd!("echo {}G | sudo tee {}/{}", gb, mem_cgroup, file))?;
+            }
+        }
+
+        cmd_prefix.push_str("sudo cgexec -g memory:fbmm_exp ");
+    }
+
+    if let Some(page_reporting) = cfg.page_reporting {
+        set_page_reporting(&ushell, page_reporting)?;
+    }
+
     if cfg.lock_stat {
         // Enable collection of statistic
         ushell.run(cmd!("echo 1 | sudo tee /proc/sys/kernel/lock_stat"))?;
@@ -972,72 +3254,138 @@ where
         // Set up the remote for FOM
         ushell.run(cmd!("mkdir -p ./daxtmp/"))?;
 
-        match fs {
-            MMFS::Ext4 { .. } => {
-                ushell.run(cmd!("sudo mkfs.ext4 /dev/pmem0"))?;
-                ushell.run(cmd!("sudo tune2fs -O ^has_journal /dev/pmem0"))?;
-                if !cfg.ext4_metadata {
-                    ushell.run(cmd!("sudo tune2fs -O ^metadata_csum /dev/pmem0"))?;
-                }
-                ushell.run(cmd!("sudo mount -o dax /dev/pmem0 daxtmp/"))?;
-            }
-            MMFS::BasicMMFS { num_pages } => {
-                ushell.run(cmd!(
-                    "sudo insmod {}/BasicMMFS/basicmmfs.ko",
-                    crate::KERNEL_PATH
-                ))?;
-                ushell.run(cmd!(
-                    "sudo mount -t BasicMMFS BasicMMFS -o numpages={} daxtmp/",
-                    num_pages,
-                ))?;
+        // A previous run on this boot may have left its mount in place. With --reuse_mount,
+        // a mount of the same FS is emptied and used as is.
+        let mounted = ushell
+            .run(cmd!(
+                "findmnt -n -o FSTYPE,SOURCE,OPTIONS {}/daxtmp",
+                &user_home
+            ))
+            .map(|out| out.stdout.trim().to_owned())
+            .unwrap_or_default();
+        let (mounted_fs, mounted_source) = {
+            let mut fields = mounted.split_whitespace();
+            (fields.next().unwrap_or(""), fields.next().unwrap_or(""))
+        };
+        let same_mount = mounted_fs == fs.fs_type() && mounted_source == mount_source(cfg, fs);
+        let reuse_mount = cfg.reuse_mount && !reuse_load && same_mount;
+
+        // The mount from the --load_only run holds the loaded data, so leave it alone.
+        // With --assume_setup, it is already mounted.
+        if reuse_mount {
+            ushell.run(cmd!("sudo rm -rf {}/daxtmp/*", &user_home).use_bash())?;
+        } else if !reuse_load && !cfg.assume_setup {
+            if same_mount {
+                return Err(failure::format_err!(
+                    "~/daxtmp/ is already mounted with the requested FS ({}). Pass --reuse_mount \
+                     to reuse it, or unmount it.",
+                    mounted
+                ));
+            } else if !mounted.is_empty() {
+                // Some other FS is in the way of the requested one
+                println!("Unmounting the existing mount of ~/daxtmp/: {}", mounted);
+                ushell.run(cmd!("sudo umount daxtmp/"))?;
             }
-            MMFS::TieredMMFS { .. } => {
-                ushell.run(cmd!(
-                    "sudo insmod {}/TieredMMFS/tieredmmfs.ko",
-                    crate::KERNEL_PATH
-                ))?;
-                ushell.run(cmd!(
-                    "sudo mount -t TieredMMFS -o slowmem=/dev/pmem1 -o basepage={} /dev/pmem0 daxtmp/",
-                    cfg.disable_thp
-                ))?;
 
-                if let Some(interval) = cfg.migrate_task_int {
-                    ushell.run(cmd!(
-                        "echo {} | sudo tee /sys/fs/tieredmmfs/migrate_task_int",
-                        interval
-                    ))?;
+            // DAX mounts need the pmem devices to be in fsdax mode
+            let pmem_devs = match fs {
+                MMFS::Ext4 | MMFS::Xfs => vec![cfg.fast_dev.as_str()],
+                MMFS::TieredMMFS => vec![cfg.fast_dev.as_str(), cfg.slow_dev.as_str()],
+                _ => vec![],
+            };
+            for dev in &pmem_devs {
+                if ushell.run(cmd!("test -b {}", dev)).is_err() {
+                    return Err(failure::format_err!("{} is not a block device", dev));
                 }
             }
-            MMFS::ContigMMFS { .. } => {
-                ushell.run(cmd!(
-                    "sudo insmod {}/ContigMMFS/contigmmfs.ko",
-                    crate::KERNEL_PATH
-                ))?;
-
-                ushell.run(cmd!("sudo mount -t ContigMMFS ContigMMFS daxtmp/"))?;
+            if !pmem_devs.is_empty() {
+                let blockdevs = pmem_devs
+                    .iter()
+                    .map(|dev| dev.trim_start_matches("/dev/"))
+                    .collect::<Vec<_>>();
+                ensure_fsdax_namespaces(&ushell, &blockdevs, &pmem_namespaces_file)?;
             }
-            MMFS::BandwidthMMFS { .. } => {
-                ushell.run(cmd!(
-                    "sudo insmod {}/BandwidthMMFS/bandwidth.ko",
-                    crate::KERNEL_PATH
-                ))?;
 
-                ushell.run(cmd!("sudo mount -t BandwidthMMFS BandwidthMMFS daxtmp/"))?;
+            if let Some((module, path)) = fs.kernel_module() {
+                insmod_if_needed(&ushell, module, &dir!(crate::KERNEL_PATH, path))?;
+            }
+            for cmd in mount_cmds(cfg, fs) {
+                ushell.run(cmd!("{}", cmd))?;
+            }
 
-                // Set the appropriate node weights
-                for weight in &cfg.node_weights {
-                    ushell.run(cmd!(
-                        "echo {} | sudo tee /sys/fs/bwmmfs*/node{}/weight",
-                        weight.weight,
-                        weight.nid
-                    ))?;
+            match fs {
+                MMFS::TieredMMFS => {
+                    // Make sure the occupancy can actually be recorded before running anything
+                    if cfg.tier_occupancy_periodic {
+                        let stats = ushell.run(cmd!("cat /sys/fs/tieredmmfs/stats"))?.stdout;
+                        if parse_tier_pages(&stats).is_none() {
+                            return Err(failure::format_err!(
+                                "/sys/fs/tieredmmfs/stats does not report the total and free \
+                                 pages of the fast and slow tiers, so --tier_occupancy_periodic \
+                                 cannot be used with this kernel:\n{}",
+                                stats
+                            ));
+                        }
+                    }
+                    if let Some(interval) = cfg.migrate_task_int {
+                        ushell.run(cmd!(
+                            "echo {} | sudo tee /sys/fs/tieredmmfs/migrate_task_int",
+                            interval
+                        ))?;
+                    }
+                }
+                MMFS::BandwidthMMFS => {
+                    // Set the weight of every node, defaulting to 1, so the run doesn't depend
+                    // on the kernel's defaults. The nids were checked after the reboot.
+                    for nid in get_numa_nodes(&ushell)? {
+                        let weight = cfg.node_weights.iter().find(|w| w.nid == nid);
+                        // BandwidthMMFS has no weight for nodes it can't allocate from, such as
+                        // nodes without memory
+                        let weight_file = format!("/sys/fs/bwmmfs*/node{}/weight", nid);
+                        if ushell.run(cmd!("test -e {}", weight_file)).is_err() {
+                            if weight.is_some() {
+                                return Err(failure::format_err!(
+                                    "--node_weight is given for node {}, but BandwidthMMFS has \
+                                     no weight for it",
+                                    nid
+                                ));
+                            }
+                            continue;
+                        }
+                        let weight = weight.map_or(1, |w| w.weight);
+                        ushell.run(cmd!("echo {} | sudo tee {}", weight, weight_file))?;
+                    }
                 }
+                _ => {}
             }
         }
+        ushell.run(cmd!(
+            "echo {} > {}",
+            if reuse_mount { "reused" } else { "new" },
+            &mount_file
+        ))?;
 
         ushell.run(cmd!("sudo chown -R $USER daxtmp/"))?;
-        ushell.run(cmd!("echo 1 | sudo tee /sys/kernel/mm/fbmm/state"))?;
+        ushell.run(cmd!("echo 1 | sudo tee {}", FBMM_STATE))?;
     }
+    inject_failure(cfg, FailureStage::Mount)?;
+
+    // Remember the AutoNUMA setting so it can be put back after the workload
+    let prev_numa_balancing = if cfg.disable_numa_balancing {
+        let prev = ushell
+            .run(cmd!("cat /proc/sys/kernel/numa_balancing"))?
+            .stdout;
+        ushell.run(cmd!("sudo sysctl kernel.numa_balancing=0"))?;
+        Some(prev.trim().to_owned())
+    } else {
+        None
+    };
+
+    let prev_turbo = if cfg.no_turbo {
+        disable_turbo(&ushell)?
+    } else {
+        None
+    };
 
     if cfg.tpp {
         // Set the NUMA policy to TPP
@@ -1069,44 +3417,56 @@ where
         // These options are not in the TPP kernel
         if let Some(fault_size) = &cfg.pte_fault_size {
             ushell.run(cmd!(
-                "echo {} | sudo tee /sys/kernel/mm/fbmm/pte_fault_size",
-                fault_size
+                "echo {} | sudo tee {}",
+                fault_size,
+                FBMM_PTE_FAULT_SIZE
             ))?;
         }
 
         // Handle disabling optimizations if requested
         if cfg.thp_temporal_zero {
-            ushell.run(cmd!(
-                "echo 0 | sudo tee /sys/kernel/mm/fbmm/nt_huge_page_zero"
-            ))?;
+            ushell.run(cmd!("echo 0 | sudo tee {}", FBMM_NT_HUGE_PAGE_ZERO))?;
         }
         if cfg.no_fpm_fix {
-            ushell.run(cmd!(
-                "echo 0 | sudo tee /sys/kernel/mm/fbmm/follow_page_mask_fix"
-            ))?;
+            ushell.run(cmd!("echo 0 | sudo tee {}", FBMM_FOLLOW_PAGE_MASK_FIX))?;
         }
         if cfg.no_pmem_write_zeroes {
-            ushell.run(cmd!(
-                "echo 0 | sudo tee /sys/kernel/mm/fbmm/pmem_write_zeroes"
-            ))?;
+            ushell.run(cmd!("echo 0 | sudo tee {}", FBMM_PMEM_WRITE_ZEROES))?;
         }
         if cfg.track_pfn_insert {
-            ushell.run(cmd!(
-                "echo 1 | sudo tee /sys/kernel/mm/fbmm/track_pfn_insert"
-            ))?;
+            ushell.run(cmd!("echo 1 | sudo tee {}", FBMM_TRACK_PFN_INSERT))?;
         }
         if cfg.mark_inode_dirty {
-            ushell.run(cmd!(
-                "echo 1 | sudo tee /sys/kernel/mm/fbmm/mark_inode_dirty"
-            ))?;
+            ushell.run(cmd!("echo 1 | sudo tee {}", FBMM_MARK_INODE_DIRTY))?;
         }
         if cfg.no_prealloc {
-            ushell.run(cmd!(
-                "echo 0 | sudo tee /sys/kernel/mm/fbmm/prealloc_map_populate"
-            ))?;
+            ushell.run(cmd!("echo 0 | sudo tee {}", FBMM_PREALLOC_MAP_POPULATE))?;
         }
     }
 
+    if cfg.mlock {
+        cmd_prefix.push_str(&format!("env LD_PRELOAD={}/mlockall.so ", bmks_dir));
+    }
+
+    if !cfg.workload_env.is_empty() {
+        let vars = cfg
+            .workload_env
+            .iter()
+            .map(|var| escape_for_bash(var))
+            .collect::<Vec<_>>()
+            .join(" ");
+        cmd_prefix.push_str(&format!("env {} ", vars));
+    }
+
+    // Running setuid programs like sudo clears the personality, so this comes after them
+    if cfg.per_proc_no_aslr {
+        cmd_prefix.push_str("setarch `uname -m` -R ");
+    }
+
+    if cfg.strace_summary {
+        cmd_prefix.push_str(&format!("strace -f -c -o {} ", &strace_file));
+    }
+
     // Badger trap will capture stats for anything "after" it in the command,
     // so it should be the last thing in the command prefix to only capture the
     // workload's staticstics
@@ -1117,7 +3477,11 @@ where
     // Start the mm_fault_tracker BPF script if requested
     let mmap_tracker_handle = if cfg.mmap_tracker {
         let spawn_handle = ushell.spawn(cmd!(
-            "sudo {}/mmap_tracker.py -c {} | tee {}",
+            "{}sudo {}/mmap_tracker.py -c {} | tee {}",
+            collector_cores
+                .as_ref()
+                .map(|cores| format!("taskset -c {} ", cores))
+                .unwrap_or_default(),
             &scripts_dir,
             &proc_name,
             &mmap_tracker_file,
@@ -1137,16 +3501,13 @@ where
             op_count,
             read_prop,
             update_prop,
+            reuse_load: false,
+            field_count,
+            field_length,
+            ..
         } => {
-            // Empirically, this is the amount of bytes a single record takes
-            const RECORD_SIZE: usize = 1350;
-            // "size" is the size in GB on the cache, so take off a GB to add some wiggle room
-            let record_count = ((size - 1) << 30) / RECORD_SIZE;
-            let client_pin_core = if let Ok(core) = tctx.next() {
-                Some(core)
-            } else {
-                None
-            };
+            let record_count = memcached_record_count(size, field_count, field_length);
+            let client_pin_core = ycsb_client_cores.first().copied();
             let memcached_cfg = MemcachedWorkloadConfig {
                 user: &login.username,
                 memcached: &memcached_dir,
@@ -1175,6 +3536,76 @@ where
                 ycsb_path: &ycsb_dir,
                 ycsb_result_file: Some(&ycsb_file),
             };
+            if field_count.is_some() || field_length.is_some() {
+                // libscail always loads YCSB's default record layout, so start the server and
+                // load it ourselves with the same layout the run phase uses.
+                start_memcached(
+                    &ushell,
+                    &memcached_dir,
+                    &login.username,
+                    size << 10,
+                    !cfg.disable_thp,
+                    &cmd_prefix,
+                    pin_cores[0],
+                )?;
+                load_ycsb_memcached(
+                    &ushell,
+                    &ycsb_dir,
+                    record_count,
+                    field_count,
+                    field_length,
+                    &ycsb_client_cores,
+                )?;
+
+                None
+            } else {
+                let mut ycsb = YcsbSession::new(ycsb_cfg);
+
+                ycsb.start_and_load(&ushell)?;
+
+                Some(ycsb)
+            }
+        }
+        Workload::Redis {
+            size,
+            op_count,
+            read_prop,
+            update_prop,
+        } => {
+            // Don't let the distro's redis take the port, and don't let snapshots or the AOF
+            // add disk I/O to the measurement
+            ushell.run(cmd!("sudo systemctl stop redis-server"))?;
+            ushell.run(cmd!(
+                "printf 'port 6379\\nsave \"\"\\nappendonly no\\nprotected-mode no\\n' \
+                 > {}",
+                redis_conf
+            ))?;
+
+            let redis_cfg = RedisWorkloadConfig {
+                redis_conf,
+                server_size_mb: size << 10,
+                wk_size_gb: size,
+                output_file: None,
+                pintool: None,
+                cmd_prefix: Some(&cmd_prefix),
+                mmu_perf: None,
+                server_start_cb: empty_func,
+                server_pin_core: Some(pin_cores[0]),
+            };
+            let ycsb_cfg = YcsbConfig {
+                workload: YcsbWorkload::Custom {
+                    record_count: memcached_record_count(size, None, None),
+                    op_count,
+                    distribution: YcsbDistribution::Zipfian,
+                    read_prop,
+                    update_prop,
+                    insert_prop: 1.0 - read_prop - update_prop,
+                },
+                system: YcsbSystem::Redis(redis_cfg),
+                client_pin_core: ycsb_client_cores.first().copied(),
+                ycsb_path: &ycsb_dir,
+                ycsb_result_file: Some(&ycsb_file),
+            };
             let mut ycsb = YcsbSession::new(ycsb_cfg);
 
             ycsb.start_and_load(&ushell)?;
@@ -1231,7 +3662,11 @@ where
     // Start the mm_fault_tracker BPF script if requested
     let mm_fault_tracker_handle = if cfg.mm_fault_tracker {
         let spawn_handle = ushell.spawn(cmd!(
-            "sudo {}/mm_fault_tracker.py -c {} | tee {}",
+            "{}sudo {}/mm_fault_tracker.py -c {} | tee {}",
+            collector_cores
+                .as_ref()
+                .map(|cores| format!("taskset -c {} ", cores))
+                .unwrap_or_default(),
             &scripts_dir,
             &proc_name,
             &mm_fault_file
@@ -1245,75 +3680,328 @@ where
         None
     };
 
-    match cfg.workload {
-        Workload::AllocTest {
-            size,
-            num_allocs,
-            threads,
-            populate,
-            touch,
-        } => {
-            time!(timers, "Workload", {
-                run_alloc_test(
-                    &ushell,
-                    &bmks_dir,
-                    size,
-                    num_allocs,
-                    threads,
-                    Some(&cmd_prefix),
-                    &alloc_test_file,
-                    &runtime_file,
-                    &pin_cores_str,
-                    populate,
-                    touch,
-                )?;
-            });
-        }
+    // Remember how much of dmesg there is so we only check the messages from the workload
+    let dmesg_start_line = if cfg.abort_on_dmesg_warn {
+        let lines = ushell.run(cmd!("sudo dmesg | wc -l"))?.stdout;
+        Some(lines.trim().parse::<usize>()?)
+    } else {
+        None
+    };
 
-        Workload::Canneal { workload } => {
-            time!(timers, "Workload", {
-                run_canneal(
-                    &ushell,
-                    &parsec_dir,
-                    workload,
-                    Some(&cmd_prefix),
-                    None,
-                    &runtime_file,
-                    pin_cores[0],
-                )?;
-            });
-        }
+    if cfg.zoneinfo {
+        ushell.run(cmd!(
+            "(echo '== before =='; cat /proc/zoneinfo) > {}",
+            &zoneinfo_file
+        ))?;
+    }
 
-        w @ Workload::Spec2017Mcf
-        | w @ Workload::Spec2017Xz { size: _ }
-        | w @ Workload::Spec2017Xalancbmk
-        | w @ Workload::Spec2017CactuBSSN => {
-            let wkload = match w {
-                Workload::Spec2017Mcf => Spec2017Workload::Mcf,
-                Workload::Spec2017Xz { size } => Spec2017Workload::Xz { size },
-                Workload::Spec2017Xalancbmk => Spec2017Workload::Xalancbmk,
-                Workload::Spec2017CactuBSSN => Spec2017Workload::CactuBSSN,
-                _ => unreachable!(),
-            };
+    if let Some(before_cmd) = &cfg.before_cmd {
+        ushell.run(cmd!("({}) 2>&1 | tee {}", before_cmd, &before_file))?;
+    }
 
-            time!(timers, "Workload", {
-                run_spec17(
-                    &ushell,
-                    &spec_dir,
-                    wkload,
-                    None,
-                    Some(&cmd_prefix),
-                    &runtime_file,
-                    pin_cores,
-                )?;
-            });
-        }
+    // Snapshot the FBMM stats so the workload's share of them can be told apart
+    if cfg.fbmm.is_some() {
+        ushell.run(cmd!("cat {} > {}", FBMM_STATS, &fbmm_stats_before_file))?;
+    }
 
-        Workload::Gups {
-            threads,
-            exp,
-            hot_exp,
-            move_hot,
+    set_phase("workload");
+    inject_failure(cfg, FailureStage::Workload)?;
+
+    // offcputime traces every process, and its stacks are filtered to the workload's later
+    let offcpu_handle = if cfg.offcpu_flame_graph {
+        Some(ushell.spawn(cmd!(
+            "{}sudo offcputime-bpfcc -f > {}",
+            collector_cores
+                .as_ref()
+                .map(|cores| format!("taskset -c {} ", cores))
+                .unwrap_or_default(),
+            offcpu_stacks_file
+        ))?)
+    } else {
+        None
+    };
+    let stress_ng_handle = if let Some(workers) = cfg.stress_ng_vm {
+        let cores = stress_ng_cores
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        // stress-ng prints its metrics to the log when it is interrupted
+        let handle = ushell.spawn(cmd!(
+            "taskset -c {} stress-ng --vm {} --vm-bytes {} --metrics-brief --log-file {}",
+            cores,
+            workers,
+            &cfg.stress_ng_vm_bytes,
+            &stress_ng_file
+        ))?;
+
+        Some(handle)
+    } else {
+        None
+    };
+
+    let rss_wait_handle = if let Some(gb) = cfg.wait_rss_gb {
+        // Poll the workload's RSS on the remote, and note when and how long it took to reach
+        // the target. It gives up when told to stop after the workload.
+        let handle = ushell.spawn(cmd!(
+            "start=`date +%s%3N`; \
+            while [ ! -e {0} ]; do \
+                pid={1}; \
+                rss=`awk '/VmRSS/ {{print $2}}' /proc/$pid/status 2>/dev/null`; \
+                if [ \"${{rss:-0}}\" -ge {2} ]; then \
+                    now=`date +%s%3N`; echo $((now - start)) > {3}; echo $now > {4}; \
+                    break; \
+                fi; \
+                sleep 0.1; \
+            done",
+            rss_wait_stop_file,
+            workload_pid_cmd(&proc_name),
+            gb << 20,
+            &time_to_rss_file,
+            rss_reached_file,
+        ))?;
+
+        // The servers are already running, so only time them from when they reach the RSS
+        if let Workload::Memcached { .. } | Workload::Redis { .. } | Workload::Postgres { .. } =
+            cfg.workload
+        {
+            // They are done loading, so don't wait forever if they stopped growing short of it
+            println!("Waiting for {} to reach {} GB RSS...", &proc_name, gb);
+            let reached = ushell.run(cmd!(
+                "timeout {} bash -c 'while [ ! -e {} ]; do sleep 1; done'",
+                SERVER_RSS_WAIT_SECONDS,
+                rss_reached_file
+            ));
+            ushell.run(cmd!("touch {}", rss_wait_stop_file))?;
+            handle.join().1?;
+            if reached.is_err() {
+                return Err(failure::format_err!(
+                    "{} did not reach {} GB RSS within {} seconds",
+                    &proc_name,
+                    gb,
+                    SERVER_RSS_WAIT_SECONDS
+                ));
+            }
+            None
+        } else {
+            Some(handle)
+        }
+    } else {
+        None
+    };
+
+    // Monitor the bandwidth of the workload's cores in their own resctrl group
+    let mbm_group = "/sys/fs/resctrl/mon_groups/fbmm_exp";
+    let mbm_start = if !cfg.mbm {
+        None
+    } else if ushell
+        .run(cmd!("test -d /sys/fs/resctrl/mon_groups"))
+        .is_err()
+    {
+        println!("WARNING: resctrl is not mounted, so memory bandwidth will not be monitored");
+        None
+    } else {
+        ushell.run(cmd!("sudo rmdir {0}; sudo mkdir {0}", mbm_group).use_bash())?;
+        ushell.run(cmd!(
+            "echo {} | sudo tee {}/cpus_list",
+            &pin_cores_str,
+            mbm_group
+        ))?;
+        Some(read_mbm(&ushell, mbm_group)?)
+    };
+
+    let tracing_dir = "/sys/kernel/debug/tracing";
+    if let Some(filter) = &cfg.ftrace {
+        ushell.run(cmd!("echo 0 | sudo tee {}/tracing_on", tracing_dir))?;
+        ushell.run(cmd!("echo | sudo tee {}/trace", tracing_dir))?;
+        if let Some(kb) = cfg.ftrace_buf_size_kb {
+            ushell.run(cmd!(
+                "echo {} | sudo tee {}/buffer_size_kb",
+                kb,
+                tracing_dir
+            ))?;
+        }
+        ushell.run(cmd!(
+            "echo function_graph | sudo tee {}/current_tracer",
+            tracing_dir
+        ))?;
+        ushell.run(cmd!(
+            "echo {} | sudo tee {}/set_ftrace_filter",
+            escape_for_bash(filter),
+            tracing_dir
+        ))?;
+        ushell.run(cmd!("echo 1 | sudo tee {}/tracing_on", tracing_dir))?;
+    }
+
+    let rapl_start = if cfg.rapl { read_rapl(&ushell)? } else { None };
+
+    let workload_start = Instant::now();
+    match cfg.workload {
+        Workload::Gups {
+            threads,
+            exp,
+            variant,
+            hot_exp,
+            move_hot,
+            num_updates,
+        } if cfg.instances_per_node => {
+            time!(timers, "Workload", {
+                run_instances_per_node(
+                    &ushell,
+                    node_cores.as_ref().unwrap(),
+                    &gups_dir,
+                    &gups_command(threads, exp, variant, hot_exp, move_hot, num_updates),
+                    Some(&cmd_prefix),
+                    &gups_file,
+                    &runtime_file,
+                )?;
+            });
+        }
+
+        Workload::Stream { .. } if cfg.instances_per_node => {
+            time!(timers, "Workload", {
+                run_instances_per_node(
+                    &ushell,
+                    node_cores.as_ref().unwrap(),
+                    &bmks_dir,
+                    "./stream",
+                    Some(&cmd_prefix),
+                    &stream_file,
+                    &runtime_file,
+                )?;
+            });
+        }
+
+        Workload::AllocTest {
+            size,
+            num_allocs,
+            threads,
+            populate,
+            touch,
+        } => {
+            time!(timers, "Workload", {
+                run_alloc_test(
+                    &ushell,
+                    &bmks_dir,
+                    size,
+                    num_allocs,
+                    threads,
+                    Some(&cmd_prefix),
+                    &alloc_test_file,
+                    &runtime_file,
+                    &pin_cores_str,
+                    populate,
+                    touch,
+                )?;
+            });
+        }
+
+        Workload::Canneal {
+            workload,
+            threads: 1,
+        } => {
+            time!(timers, "Workload", {
+                run_canneal(
+                    &ushell,
+                    &parsec_dir,
+                    workload,
+                    Some(&cmd_prefix),
+                    None,
+                    &runtime_file,
+                    pin_cores[0],
+                )?;
+            });
+        }
+
+        Workload::Canneal { workload, threads } => {
+            time!(timers, "Workload", {
+                run_canneal_threaded(
+                    &ushell,
+                    &parsec_dir,
+                    workload,
+                    threads,
+                    Some(&cmd_prefix),
+                    &runtime_file,
+                    &pin_cores_str,
+                )?;
+            });
+        }
+
+        ref w @ Workload::Spec2017Mcf
+        | ref w @ Workload::Spec2017Xz { size: _ }
+        | ref w @ Workload::Spec2017Xalancbmk
+        | ref w @ Workload::Spec2017CactuBSSN
+        | ref w @ Workload::Spec2017Lbm
+        | ref w @ Workload::Spec2017Bwaves
+        | ref w @ Workload::Spec2017Roms
+        | ref w @ Workload::Spec2017Fotonik3d
+        | ref w @ Workload::Spec2017Omnetpp => {
+            // libscail only knows how to run some of the speed benchmarks itself
+            let wkload = match *w {
+                Workload::Spec2017Mcf => Some(Spec2017Workload::Mcf),
+                Workload::Spec2017Xz { size } => Some(Spec2017Workload::Xz { size }),
+                Workload::Spec2017Xalancbmk => Some(Spec2017Workload::Xalancbmk),
+                Workload::Spec2017CactuBSSN => Some(Spec2017Workload::CactuBSSN),
+                _ => None,
+            };
+
+            // libscail runs with the tuning of the SPEC config, so peak runs go through runcpu,
+            // which is passed the tuning, rather than editing the user's config
+            let tune = match cfg.spec_tune {
+                Some(SpecTune::Peak) => "peak",
+                _ => "base",
+            };
+            let spec_config = dir!(&spec_dir, "config/spec-linux-x86.cfg");
+            if tune == "peak" {
+                // Make sure the config actually has a peak section (e.g. `default=peak:` or
+                // `505.mcf_r=peak:`) instead of silently falling back to base. Comments
+                // mentioning peak don't count.
+                ushell
+                    .run(cmd!("grep -Eq '^[^#]*=peak|:peak:' {}", spec_config))
+                    .map_err(|_| {
+                        failure::format_err!("{} does not define any peak settings", spec_config)
+                    })?;
+            }
+
+            match wkload {
+                Some(wkload) if cfg.spec_rate.is_none() && tune == "base" => {
+                    time!(timers, "Workload", {
+                        run_spec17(
+                            &ushell,
+                            &spec_dir,
+                            wkload,
+                            None,
+                            Some(&cmd_prefix),
+                            &runtime_file,
+                            pin_cores,
+                        )?;
+                    });
+                }
+                _ => {
+                    let benchmark = w.spec_benchmark(cfg.spec_rate.is_some()).unwrap();
+                    time!(timers, "Workload", {
+                        run_spec17_runcpu(
+                            &ushell,
+                            &spec_dir,
+                            benchmark,
+                            cfg.spec_rate,
+                            tune,
+                            Some(&cmd_prefix),
+                            &spec_copies_file,
+                            &runtime_file,
+                            &pin_cores,
+                        )?;
+                    });
+                }
+            }
+        }
+
+        Workload::Gups {
+            threads,
+            exp,
+            variant,
+            hot_exp,
+            move_hot,
             num_updates,
         } => {
             time!(timers, "Workload", {
@@ -1322,11 +4010,13 @@ where
                     &gups_dir,
                     threads,
                     exp,
+                    variant,
                     hot_exp,
                     move_hot,
                     num_updates,
                     Some(&cmd_prefix),
                     &gups_file,
+                    &gups_metric_file,
                     &runtime_file,
                     &pin_cores_str,
                 )?;
@@ -1347,11 +4037,74 @@ where
             });
         }
 
-        Workload::Memcached { .. } => {
-            let mut ycsb = ycsb.unwrap();
+        Workload::Memcached {
+            load_only: true, ..
+        } => {
+            // Leave the loaded server running for later --reuse_load runs
+            println!("memcached is loaded. Use --reuse_load to run the workload against it.");
+        }
+
+        Workload::Memcached {
+            size,
+            op_count,
+            read_prop,
+            update_prop,
+            reuse_load: true,
+            field_count,
+            field_length,
+            run_threads,
+            ..
+        } => {
+            time!(timers, "Workload", {
+                run_ycsb_preloaded_memcached(
+                    &ushell,
+                    &ycsb_dir,
+                    memcached_record_count(size, field_count, field_length),
+                    op_count,
+                    read_prop,
+                    update_prop,
+                    field_count,
+                    field_length,
+                    &ycsb_file,
+                    &ycsb_client_cores,
+                    run_threads,
+                )?;
+            });
+        }
 
+        Workload::Memcached {
+            size,
+            op_count,
+            read_prop,
+            update_prop,
+            field_count,
+            field_length,
+            run_threads,
+            ..
+        } => {
             //Run the workload
-            time!(timers, "Workload", ycsb.run(&ushell))?;
+            if run_threads.is_some() || ycsb.is_none() {
+                // libscail only runs YCSB with one client thread and the default record
+                // layout, so run it ourselves
+                time!(timers, "Workload", {
+                    run_ycsb_preloaded_memcached(
+                        &ushell,
+                        &ycsb_dir,
+                        memcached_record_count(size, field_count, field_length),
+                        op_count,
+                        read_prop,
+                        update_prop,
+                        field_count,
+                        field_length,
+                        &ycsb_file,
+                        &ycsb_client_cores,
+                        run_threads,
+                    )?;
+                });
+            } else {
+                let mut ycsb = ycsb.unwrap();
+                time!(timers, "Workload", ycsb.run(&ushell))?;
+            }
 
             // Make sure the server dies.
             ushell.run(cmd!("sudo pkill -INT memcached"))?;
@@ -1362,6 +4115,20 @@ where
             std::thread::sleep(std::time::Duration::from_secs(20));
         }
 
+        Workload::Redis { .. } => {
+            let mut ycsb = ycsb.unwrap();
+
+            //Run the workload
+            time!(timers, "Workload", ycsb.run(&ushell))?;
+
+            // Make sure the server dies.
+            ushell.run(cmd!(
+                "redis-cli shutdown nosave || sudo pkill -INT redis-server"
+            ))?;
+            while let Ok(..) = ushell.run(cmd!("pgrep -x redis-server")) {}
+            std::thread::sleep(std::time::Duration::from_secs(20));
+        }
+
         Workload::Postgres { .. } => {
             let mut ycsb = ycsb.unwrap();
 
@@ -1388,6 +4155,34 @@ where
             });
         }
 
+        Workload::Rocksdb {
+            ref benchmarks,
+            num,
+            value_size,
+        } => {
+            // Put the DB on the FBMM mount so its files go through the MMFS
+            let db_dir = if cfg.fbmm.is_some() {
+                dir!(&user_home, "daxtmp/rocksdb")
+            } else {
+                "/tmp/rocksdb".into()
+            };
+
+            time!(timers, "Workload", {
+                run_rocksdb(
+                    &ushell,
+                    &rocksdb_dir,
+                    &db_dir,
+                    benchmarks,
+                    num,
+                    value_size,
+                    Some(&cmd_prefix),
+                    &rocksdb_file,
+                    &runtime_file,
+                    pin_cores[0],
+                )?;
+            });
+        }
+
         Workload::Stream { .. } => {
             time!(timers, "Workload", {
                 run_stream(
@@ -1400,56 +4195,468 @@ where
                 )?;
             })
         }
-    }
 
-    // If we are using FBMM, print some stats
-    if let Some(fs) = &cfg.fbmm {
-        ushell.run(cmd!(
-            "cat /sys/kernel/mm/fbmm/stats | tee {}",
-            &fbmm_stats_file
-        ))?;
+        Workload::FileRead {
+            file_size_gb,
+            threads,
+            pattern,
+        } => {
+            // Put the file on the FBMM mount so we exercise its read fault path
+            let file_dir = if cfg.fbmm.is_some() {
+                dir!(&user_home, "daxtmp/")
+            } else {
+                "/tmp/".into()
+            };
 
-        match fs {
-            // If we are using TieredMMFS, print some more stats
-            MMFS::TieredMMFS { .. } => {
-                ushell.run(cmd!(
-                    "cat /sys/fs/tieredmmfs/stats | tee {}",
-                    &tieredmmfs_stats_file
-                ))?;
-            }
-            _ => {}
+            time!(timers, "Workload", {
+                run_fileread(
+                    &ushell,
+                    &bmks_dir,
+                    &file_dir,
+                    file_size_gb,
+                    threads,
+                    pattern,
+                    Some(&cmd_prefix),
+                    &file_read_file,
+                    &runtime_file,
+                    &pin_cores_str,
+                )?;
+            })
         }
-    }
 
-    ushell.run(cmd!("cat /proc/vmstat | tee {}", &vmstat_file))?;
-
-    // Generate the flamegraph if needed
-    if cfg.flame_graph {
-        ushell.run(cmd!(
-            "sudo perf script -i {} | ./FlameGraph/stackcollapse-perf.pl > /tmp/flamegraph",
-            &perf_record_file,
-        ))?;
-        ushell.run(cmd!(
-            "./FlameGraph/flamegraph.pl /tmp/flamegraph > {}",
-            flame_graph_file
-        ))?;
-    }
+        Workload::Gap {
+            kernel,
+            scale,
+            threads,
+            trials,
+        } => {
+            // Put the graph on the FBMM mount so loading it goes through FBMM
+            let graph_dir = if cfg.fbmm.is_some() {
+                dir!(&user_home, "daxtmp/")
+            } else {
+                "/tmp/".into()
+            };
 
-    // Record the lock statistics if needed
-    if cfg.lock_stat {
-        ushell.run(cmd!(
-            "sudo cat /proc/lock_stat | sudo tee {}",
-            lock_stat_file
-        ))?;
-    }
+            time!(timers, "Workload", {
+                run_gap(
+                    &ushell,
+                    &gap_dir,
+                    &graph_dir,
+                    kernel,
+                    scale,
+                    threads,
+                    trials,
+                    Some(&cmd_prefix),
+                    &gap_file,
+                    &gap_trials_file,
+                    &runtime_file,
+                    &pin_cores_str,
+                )?;
+            })
+        }
 
-    // Record the badger trap stats if needed
-    if cfg.badger_trap {
-        ushell.run(cmd!("dmesg | tail -n 10 | sudo tee {}", badger_trap_file))?;
-    }
+        Workload::CacheBench { max_size, stride } => {
+            time!(timers, "Workload", {
+                run_cachebench(
+                    &ushell,
+                    &bmks_dir,
+                    max_size,
+                    stride,
+                    Some(&cmd_prefix),
+                    &cachebench_file,
+                    &runtime_file,
+                    pin_cores[0],
+                )?;
+            })
+        }
 
-    // Get DAMO stats if we use HMSDK 2.0
-    if cfg.hmsdk_tiered {
+        Workload::Idle { duration } => {
+            time!(timers, "Workload", {
+                run_idle(
+                    &ushell,
+                    duration,
+                    Some(&cmd_prefix),
+                    &runtime_file,
+                    pin_cores[0],
+                )?;
+            })
+        }
+
+        Workload::Xsbench {
+            ref size,
+            lookups,
+            threads,
+        } => {
+            time!(timers, "Workload", {
+                run_xsbench(
+                    &ushell,
+                    &xsbench_dir,
+                    size,
+                    lookups,
+                    threads,
+                    Some(&cmd_prefix),
+                    &xsbench_file,
+                    &runtime_file,
+                    &pin_cores_str,
+                )?;
+            })
+        }
+
+        Workload::Multichase {
+            mem_size,
+            ref chase,
+        } => {
+            time!(timers, "Workload", {
+                run_multichase(
+                    &ushell,
+                    &bmks_dir,
+                    mem_size,
+                    chase,
+                    Some(&cmd_prefix),
+                    &multichase_file,
+                    &runtime_file,
+                    pin_cores[0],
+                )?;
+            })
+        }
+
+        Workload::Custom {
+            ref binary,
+            ref args,
+            ref cwd,
+            ..
+        } => {
+            time!(timers, "Workload", {
+                run_custom(
+                    &ushell,
+                    binary,
+                    args,
+                    cwd.as_deref().unwrap_or(&user_home),
+                    Some(&cmd_prefix),
+                    &custom_file,
+                    &runtime_file,
+                    &pin_cores_str,
+                )?;
+            })
+        }
+    }
+
+    if let Some(metrics) = &metrics {
+        metrics.set_runtime_ms(workload_start.elapsed().as_millis());
+    }
+
+    if let Some((total_start, local_start)) = mbm_start {
+        let (total_end, local_end) = read_mbm(&ushell, mbm_group)?;
+        ushell.run(cmd!(
+            "echo -e 'mbm_total_bytes {}\\nmbm_local_bytes {}' > {}",
+            total_end.saturating_sub(total_start),
+            local_end.saturating_sub(local_start),
+            &mbm_file
+        ))?;
+        ushell.run(cmd!("sudo rmdir {}", mbm_group))?;
+    }
+
+    if cfg.rapl {
+        let seconds = workload_start.elapsed().as_secs_f64();
+        let energy = match (rapl_start, read_rapl(&ushell)?) {
+            (Some(start), Some(end)) => start
+                .iter()
+                .zip(end.iter())
+                .map(|((name, start, max), (_, end, _))| {
+                    // The counters wrap around at max_energy_range_uj
+                    let uj = if end >= start {
+                        end - start
+                    } else {
+                        max - start + end
+                    };
+                    let joules = uj as f64 / 1e6;
+                    format!(
+                        "{0}_joules {1:.3}\n{0}_watts {2:.3}",
+                        name,
+                        joules,
+                        joules / seconds
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => "unavailable".into(),
+        };
+        ushell.run(cmd!("echo {} > {}", escape_for_bash(&energy), &rapl_file))?;
+    }
+
+    if cfg.damon.is_some() {
+        ushell.run(cmd!("echo off | sudo tee {}/state || true", kdamond))?;
+    }
+
+    if cfg.ftrace.is_some() {
+        ushell.run(cmd!("echo 0 | sudo tee {}/tracing_on", tracing_dir))?;
+        ushell.run(cmd!("sudo cat {}/trace > {}", tracing_dir, &ftrace_file))?;
+        ushell.run(cmd!("echo nop | sudo tee {}/current_tracer", tracing_dir))?;
+        ushell.run(cmd!("echo | sudo tee {}/set_ftrace_filter", tracing_dir))?;
+        ushell.run(cmd!("echo | sudo tee {}/trace", tracing_dir))?;
+    }
+
+    // offcputime prints the stacks when it is interrupted. The brackets keep pkill from
+    // matching the shell running it.
+    if let Some(handle) = offcpu_handle {
+        ushell.run(cmd!("sudo pkill -INT -f '[o]ffcputime-bpfcc'"))?;
+        handle.join().1?;
+    }
+
+    // Stop the pressure before collecting so it doesn't show up in the stats
+    if let Some(handle) = stress_ng_handle {
+        ushell.run(cmd!("killall -SIGINT stress-ng"))?;
+        handle.join().1?;
+    }
+
+    if let Some(handle) = rss_wait_handle {
+        ushell.run(cmd!("touch {}", rss_wait_stop_file))?;
+        handle.join().1?;
+        if ushell.run(cmd!("test -e {}", rss_reached_file)).is_ok() {
+            ushell.run(cmd!(
+                "echo $((`date +%s%3N` - `cat {}`)) > {}",
+                rss_reached_file,
+                &steady_runtime_file
+            ))?;
+        } else {
+            println!(
+                "WARNING: {} never reached {} GB RSS, so there is no steady state runtime",
+                &proc_name,
+                cfg.wait_rss_gb.unwrap()
+            );
+        }
+    }
+
+    if let Some(after_cmd) = &cfg.after_cmd {
+        ushell.run(cmd!("({}) 2>&1 | tee {}", after_cmd, &after_file))?;
+    }
+    set_phase("collect");
+    inject_failure(cfg, FailureStage::Collect)?;
+
+    // If we are using FBMM, print some stats
+    let mut alloc_failures = 0;
+    if let Some(fs) = &cfg.fbmm {
+        ushell.run(cmd!("cat {} | tee {}", FBMM_STATS, &fbmm_stats_file))?;
+
+        match fs {
+            // If we are using TieredMMFS, print some more stats
+            MMFS::TieredMMFS { .. } => {
+                ushell.run(cmd!(
+                    "cat /sys/fs/tieredmmfs/stats | tee {}",
+                    &tieredmmfs_stats_file
+                ))?;
+
+                if cfg.tier_occupancy_periodic {
+                    let raw = ushell
+                        .run(cmd!("cat {} 2>/dev/null || true", &tier_occupancy_raw_file))?
+                        .stdout;
+                    ushell.run(cmd!("rm -f {}", &tier_occupancy_file))?;
+                    // Write in chunks so no single argument gets too long
+                    for chunk in tier_occupancy_lines(&raw).chunks(1000) {
+                        ushell.run(cmd!(
+                            "echo {} >> {}",
+                            escape_for_bash(&chunk.join("\n")),
+                            &tier_occupancy_file
+                        ))?;
+                    }
+                }
+
+                // Also keep the final value of every other TieredMMFS file
+                let files = ushell
+                    .run(cmd!("cd /sys/fs/tieredmmfs/ && find . -type f | sort"))?
+                    .stdout;
+                for file in files.lines().map(|f| f.trim_start_matches("./")) {
+                    if file == "stats" || file.is_empty() {
+                        continue;
+                    }
+                    let result_file = dir!(
+                        &results_dir,
+                        cfg.gen_file_name(&format!("tieredmmfs_{}", file.replace('/', "_")))
+                    );
+                    if ushell
+                        .run(cmd!(
+                            "sudo cat /sys/fs/tieredmmfs/{} > {}",
+                            file,
+                            result_file
+                        ))
+                        .is_err()
+                    {
+                        println!("WARNING: unable to read /sys/fs/tieredmmfs/{}", file);
+                        ushell.run(cmd!("rm -f {}", result_file))?;
+                    }
+                }
+            }
+            // BasicMMFS and ContigMMFS can run out of memory to give out, so record how
+            // many allocations failed
+            MMFS::BasicMMFS { .. } | MMFS::ContigMMFS => {
+                let mmfs_name = if let MMFS::BasicMMFS { .. } = fs {
+                    "basicmmfs"
+                } else {
+                    "contigmmfs"
+                };
+                let stats = ushell
+                    .run(cmd!(
+                        "cat /sys/fs/{}*/stats | tee -a {}",
+                        mmfs_name,
+                        &fbmm_stats_file
+                    ))?
+                    .stdout;
+                alloc_failures = count_alloc_failures(&stats);
+            }
+            _ => {}
+        }
+    }
+
+    ushell.run(cmd!("cat /proc/vmstat | tee {}", &vmstat_file))?;
+
+    if cfg.zoneinfo {
+        ushell.run(cmd!(
+            "(echo '== after =='; cat /proc/zoneinfo) >> {}",
+            &zoneinfo_file
+        ))?;
+    }
+
+    if let Some(prev) = &prev_numa_balancing {
+        ushell.run(cmd!("sudo sysctl kernel.numa_balancing={}", prev))?;
+    }
+    if let Some((knob, prev)) = &prev_turbo {
+        ushell.run(cmd!("echo {} | sudo tee {}", prev, knob))?;
+    }
+
+    if let Some(max_mb) = cfg.active_list_max_mb {
+        if ushell
+            .run(cmd!("test -e {}", active_list_truncated_file))
+            .is_ok()
+        {
+            println!(
+                "WARNING: the active list collection was truncated at {} MB and is incomplete.",
+                max_mb
+            );
+        }
+    }
+
+    if cfg.sched_stats {
+        let sched_stats = ushell
+            .run(cmd!(
+                "cat {} {} | tee {}",
+                sched_perf_file,
+                sched_status_file,
+                &sched_stats_file
+            ))?
+            .stdout;
+        // A pinned single core workload should never migrate
+        let migrations = sched_stats
+            .lines()
+            .find(|line| line.contains("cpu-migrations"))
+            .and_then(|line| line.split_whitespace().next())
+            .and_then(|count| count.replace(',', "").parse::<usize>().ok())
+            .unwrap_or(0);
+        if num_pin_cores == 1 && migrations > 0 {
+            println!(
+                "WARNING: the workload migrated between cores {} times. Pinning may not have worked.",
+                migrations
+            );
+        }
+    }
+
+    // Look for any kernel warnings that happened during the workload
+    let dmesg_warn = if let Some(start_line) = dmesg_start_line {
+        let warnings = ushell
+            .run(cmd!(
+                "sudo dmesg | tail -n +{} | (grep -E 'WARNING|BUG|RIP|Call Trace' || true) | tee {}",
+                start_line + 1,
+                &dmesg_warn_file
+            ))?
+            .stdout;
+        !warnings.trim().is_empty()
+    } else {
+        false
+    };
+
+    // The MMFS modules report some of their problems only in dmesg
+    ushell.run(cmd!("sudo dmesg | tail -n 200 | tee {}", &dmesg_file))?;
+
+    // Generate the flamegraph if needed
+    if cfg.flame_graph && cfg.perf_switch_output.is_some() {
+        // perf names each segment with a timestamp, so they sort in order
+        let segments = ushell.run(cmd!("ls -1 {}.*", &perf_record_file))?.stdout;
+        for (i, segment) in segments.lines().enumerate() {
+            let segment_flame_graph_file = dir!(
+                &results_dir,
+                cfg.gen_file_name(&format!("flamegraph.{}.svg", i))
+            );
+            ushell.run(cmd!(
+                "sudo perf script -i {} | ./FlameGraph/stackcollapse-perf.pl > /tmp/flamegraph",
+                segment.trim(),
+            ))?;
+            ushell.run(cmd!(
+                "./FlameGraph/flamegraph.pl /tmp/flamegraph > {}",
+                segment_flame_graph_file
+            ))?;
+        }
+    } else if cfg.flame_graph {
+        ushell.run(cmd!(
+            "sudo perf script -i {} | ./FlameGraph/stackcollapse-perf.pl > /tmp/flamegraph",
+            &perf_record_file,
+        ))?;
+        ushell.run(cmd!(
+            "./FlameGraph/flamegraph.pl /tmp/flamegraph > {}",
+            flame_graph_file
+        ))?;
+    }
+
+    if let Some(tool) = perf_sample_tool {
+        let data_file = dir!(
+            &results_dir,
+            cfg.gen_file_name(&format!("perf_{}.data", tool))
+        );
+        let report_file = dir!(
+            &results_dir,
+            cfg.gen_file_name(&format!("perf_{}_report", tool))
+        );
+        ushell.run(cmd!(
+            "sudo perf {} report --stdio -i {} > {}",
+            tool,
+            perf_sample_record_file,
+            report_file
+        ))?;
+        ushell.run(cmd!("sudo mv {} {}", perf_sample_record_file, data_file))?;
+    }
+
+    if cfg.offcpu_flame_graph {
+        // The folded stacks start with the name of the process
+        ushell.run(cmd!(
+            "(grep '^{};' {} || true) | ./FlameGraph/flamegraph.pl --color=io \
+             --title='Off-CPU Time Flame Graph' --countname=us > {}",
+            &proc_name,
+            offcpu_stacks_file,
+            offcpu_flame_graph_file
+        ))?;
+    }
+
+    // Record the lock statistics if needed
+    if cfg.lock_stat {
+        ushell.run(cmd!(
+            "sudo cat /proc/lock_stat | sudo tee {}",
+            lock_stat_file
+        ))?;
+    }
+
+    // Record the badger trap stats if needed
+    if cfg.badger_trap {
+        ushell.run(cmd!("dmesg | tail -n 10 | sudo tee {}", badger_trap_file))?;
+    }
+
+    if cfg.mem_high.is_some() || cfg.mem_max.is_some() {
+        ushell.run(cmd!(
+            "cat {}/memory.events | tee {}",
+            mem_cgroup,
+            &cgroup_file
+        ))?;
+    }
+
+    // Get DAMO stats if we use HMSDK 2.0
+    if cfg.hmsdk_tiered {
         ushell.run(cmd!("sudo {}/damo/damo status | sudo tee {}", hmsdk_dir, damo_status_file))?;
     }
 
@@ -1473,26 +4680,135 @@ where
         dir!(&results_dir, time_file)
     ))?;
 
+    // Don't report results that may have been corrupted by a kernel bug
+    if dmesg_warn {
+        return Err(failure::format_err!(
+            "Kernel warnings found in dmesg during the workload. See {}",
+            dmesg_warn_file
+        ));
+    }
+
     let glob = cfg.gen_file_name("");
+
+    if alloc_failures > 0 {
+        println!(
+            "WARNING: {} allocations failed in the MM filesystem. See {}",
+            alloc_failures, fbmm_stats_file
+        );
+    }
+
+    // Failing to save the results to git shouldn't fail the experiment
+    if let Some(repo) = &cfg.results_git {
+        if let Err(e) = commit_results_to_git(&ushell, cfg, &results_dir, &glob, repo) {
+            println!("WARNING: unable to commit results to {}: {}", repo, e);
+        }
+    }
+
+    set_phase("done");
     println!("RESULTS: {}", dir!(&results_dir, glob));
     Ok(())
 }
 
-fn connect_and_setup_host<A>(login: &Login<A>) -> Result<SshShell, failure::Error>
+fn commit_results_to_git(
+    ushell: &SshShell,
+    cfg: &Config,
+    results_dir: &str,
+    glob: &str,
+    repo: &str,
+) -> Result<(), failure::Error> {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(cfg)?.hash(&mut hasher);
+    let cfg_hash = hasher.finish();
+
+    ushell.run(cmd!("cp {}* {}", dir!(results_dir, glob), repo))?;
+    ushell.run(cmd!("git add -A").cwd(repo))?;
+    ushell.run(
+        cmd!(
+            "git commit -m {}",
+            escape_for_bash(&format!("Results for {} (config {:016x})", glob, cfg_hash))
+        )
+        .cwd(repo),
+    )?;
+
+    if cfg.results_git_push {
+        ushell.run(cmd!("git push").cwd(repo))?;
+    }
+
+    Ok(())
+}
+
+/// Bounds on how long `connect_and_setup_host` waits for the remote to come back.
+pub(crate) struct RebootLimits {
+    /// How long to wait after `sudo reboot` before the first reconnect attempt.
+    pub initial_sleep: std::time::Duration,
+    /// Give up after this long since the reboot.
+    pub timeout: Option<std::time::Duration>,
+    /// Give up after this many failed reconnect attempts.
+    pub retries: Option<usize>,
+}
+
+impl Default for RebootLimits {
+    fn default() -> Self {
+        RebootLimits {
+            initial_sleep: std::time::Duration::from_secs(5),
+            timeout: None,
+            retries: None,
+        }
+    }
+}
+
+/// Reboot the remote and wait for it to come back. If `power_cycle_cmd` is given, it is run
+/// on the driver whenever the remote hasn't come back after `power_cycle_timeout`. Returns an
+/// error if the remote doesn't come back within `limits`.
+pub(crate) fn connect_and_setup_host<A>(
+    login: &Login<A>,
+    reboot: bool,
+    power_cycle_cmd: Option<&str>,
+    power_cycle_timeout: std::time::Duration,
+    limits: &RebootLimits,
+) -> Result<SshShell, failure::Error>
 where
     A: std::net::ToSocketAddrs + std::fmt::Display + std::fmt::Debug + Clone,
 {
     let ushell = SshShell::with_any_key(login.username, &login.host)?;
-    //    spurs_util::reboot(&mut ushell, /* dry_run */ false)?;
-    let _ = ushell.run(cmd!("sudo reboot"));
-    // It sometimes takes a few seconds for the reboot to actually happen,
-    // so make sure we wait a bit for it.
-    std::thread::sleep(std::time::Duration::from_secs(5));
-
-    // Keep trying to connect until we succeed
-    let ushell = {
+    let ushell = if !reboot {
+        ushell
+    } else {
+        //    spurs_util::reboot(&mut ushell, /* dry_run */ false)?;
+        let _ = ushell.run(cmd!("sudo reboot"));
+        // It sometimes takes a few seconds for the reboot to actually happen,
+        // so make sure we wait a bit for it.
+        std::thread::sleep(limits.initial_sleep);
+
+        // Keep trying to connect until we succeed or run out of time or retries
+        let start = Instant::now();
+        let mut reboot_start = Instant::now();
+        let mut attempts = 0;
         let mut shell;
         loop {
+            if limits
+                .timeout
+                .map_or(false, |timeout| start.elapsed() > timeout)
+                || limits.retries.map_or(false, |retries| attempts >= retries)
+            {
+                return Err(failure::format_err!(
+                    "{} never came back from the reboot ({} reconnect attempts over {}s)",
+                    login.hostname,
+                    attempts,
+                    start.elapsed().as_secs()
+                ));
+            }
+            attempts += 1;
+
+            if let Some(power_cycle_cmd) = power_cycle_cmd {
+                if reboot_start.elapsed() > power_cycle_timeout {
+                    power_cycle(power_cycle_cmd, &login.hostname)?;
+                    reboot_start = Instant::now();
+                }
+            }
+
             println!("Attempting to reconnect...");
             shell = match SshShell::with_any_key(login.username, &login.host) {
                 Ok(shell) => shell,
@@ -1510,167 +4826,1301 @@ where
             }
         }
 
-        shell
-    };
+        shell
+    };
+
+    check_booted_kernel(&ushell)?;
+    dump_sys_info(&ushell)?;
+
+    ushell.run(cmd!(
+        "sudo LD_LIBRARY_PATH=/usr/lib64/ cpupower frequency-set -g performance",
+    ))?;
+    ushell.run(cmd!("lscpu"))?;
+    set_kernel_printk_level(&ushell, 5)?;
+
+    Ok(ushell)
+}
+
+/// Run the given power cycle command on the driver to force the remote to reboot.
+fn power_cycle<H: std::fmt::Display>(
+    power_cycle_cmd: &str,
+    host: &H,
+) -> Result<(), failure::Error> {
+    let power_cycle_cmd = power_cycle_cmd.replace("{host}", &host.to_string());
+    println!(
+        "The remote did not come back. Power cycling with `{}`",
+        power_cycle_cmd
+    );
+
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(&power_cycle_cmd)
+        .status()?;
+    if !status.success() {
+        println!("WARNING: the power cycle command failed with {}", status);
+    }
+
+    Ok(())
+}
+
+/// Make sure the remote booted the kernel `setup_kernel` installed rather than falling back to
+/// another one (e.g., if GRUB_DEFAULT didn't stick).
+fn check_booted_kernel(ushell: &SshShell) -> Result<(), failure::Error> {
+    let expected: Option<String> = libscail::get_remote_research_setting(ushell, "kernel-release")?;
+    let expected = match expected {
+        Some(expected) => expected,
+        // The kernel wasn't installed by setup_kernel, so there is nothing to check
+        None => return Ok(()),
+    };
+
+    let booted = ushell.run(cmd!("uname -r"))?.stdout;
+    let booted = booted.trim();
+    if booted != expected {
+        return Err(failure::format_err!(
+            "The remote booted kernel `{}`, but setup_kernel installed `{}`. Check the grub \
+             default entry on the remote.",
+            booted,
+            expected
+        ));
+    }
+
+    Ok(())
+}
+
+/// Make sure the remote was booted with exactly the kernel command line options that this
+/// run would have set before rebooting.
+fn check_booted_options(
+    ushell: &SshShell,
+    cfg: &Config,
+    isolated_cores: Option<&std::ops::Range<usize>>,
+) -> Result<(), failure::Error> {
+    // The same options that are cleared from the grub config before adding ours
+    const MANAGED_OPTIONS: &[&str] = &[
+        "memmap=",
+        "do_tpp",
+        "maxcpus=",
+        "isolcpus=",
+        "nohz_full=",
+        "rcu_nocbs=",
+        "default_hugepagesz=",
+        "hugepagesz=",
+        "hugepages=",
+    ];
+
+    let cmdline = ushell.run(cmd!("cat /proc/cmdline"))?.stdout;
+    let mut booted = cmdline
+        .split_whitespace()
+        .filter(|opt| MANAGED_OPTIONS.iter().any(|m| opt.starts_with(m)))
+        .collect::<Vec<_>>();
+    let expected = boot_options(cfg, isolated_cores).join(" ");
+    let mut expected = expected.split_whitespace().collect::<Vec<_>>();
+    booted.sort_unstable();
+    expected.sort_unstable();
+
+    if booted != expected {
+        return Err(failure::format_err!(
+            "The remote was booted with `{}`, but this run needs `{}`. Run without --no_reboot.",
+            booted.join(" "),
+            expected.join(" ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compare the memmap regions we asked for with the persistent memory ranges the kernel
+/// actually reserved, recording both in `reservation_check_file`.
+fn check_reservations(
+    ushell: &SshShell,
+    cfg: &Config,
+    reservation_check_file: &str,
+) -> Result<(), failure::Error> {
+    // The lines look like "  100000000-4ffffffff : Persistent Memory (legacy)"
+    let iomem = ushell.run(cmd!("sudo cat /proc/iomem"))?.stdout;
+    let reserved = iomem
+        .lines()
+        .filter(|line| line.contains("Persistent Memory"))
+        .filter_map(|line| {
+            let (range, _) = line.trim().split_once(" : ")?;
+            let (start, end) = range.split_once('-')?;
+            Some((
+                u64::from_str_radix(start, 16).ok()?,
+                u64::from_str_radix(end, 16).ok()?,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = Vec::new();
+    let mut mismatch = false;
+    for (name, region) in [("dram", &cfg.dram_region), ("pmem", &cfg.pmem_region)] {
+        if let Some(region) = region {
+            let start = (region.start as u64) << 30;
+            let end = start + ((region.size as u64) << 30) - 1;
+            let found = reserved
+                .iter()
+                .any(|&(res_start, res_end)| res_start <= start && end <= res_end);
+            mismatch |= !found;
+            report.push(format!(
+                "requested {} {:#x}-{:#x} {}",
+                name,
+                start,
+                end,
+                if found { "ok" } else { "MISSING" }
+            ));
+        }
+    }
+    for (start, end) in &reserved {
+        report.push(format!("reserved {:#x}-{:#x}", start, end));
+    }
+
+    ushell.run(cmd!(
+        "echo {} | tee {}",
+        escape_for_bash(&report.join("\n")),
+        reservation_check_file
+    ))?;
+    if mismatch {
+        println!(
+            "WARNING: the reserved memory does not match the requested regions. See {}",
+            reservation_check_file
+        );
+    }
+
+    Ok(())
+}
+
+/// Turn off turbo boost with whichever knob this machine has. Returns the knob and its
+/// previous value so it can be restored.
+fn disable_turbo(ushell: &SshShell) -> Result<Option<(&'static str, String)>, failure::Error> {
+    // intel_pstate has a no_turbo knob, while acpi-cpufreq (e.g., on AMD) has a boost knob
+    const TURBO_KNOBS: &[(&str, &str)] = &[
+        ("/sys/devices/system/cpu/intel_pstate/no_turbo", "1"),
+        ("/sys/devices/system/cpu/cpufreq/boost", "0"),
+    ];
+
+    for (knob, off) in TURBO_KNOBS {
+        if ushell.run(cmd!("test -e {}", knob)).is_ok() {
+            let prev = ushell.run(cmd!("cat {}", knob))?.stdout;
+            ushell.run(cmd!("echo {} | sudo tee {}", off, knob))?;
+            return Ok(Some((*knob, prev.trim().to_owned())));
+        }
+    }
+
+    println!("WARNING: unable to find a knob to turn off turbo boost");
+    Ok(None)
+}
+
+/// Return an error if the run was asked to fail at this stage with --inject_failure.
+fn inject_failure(cfg: &Config, stage: FailureStage) -> Result<(), failure::Error> {
+    if cfg.inject_failure == Some(stage) {
+        Err(failure::format_err!(
+            "Injected failure at the {:?} stage",
+            stage
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Turn free page reporting on or off. The kernel has no switch for it: it is on when a driver,
+/// which is only ever virtio-balloon with the free page reporting feature, registers with it.
+fn set_page_reporting(ushell: &SshShell, on: bool) -> Result<(), failure::Error> {
+    const ORDER_PARAM: &str = "/sys/module/page_reporting/parameters/page_reporting_order";
+    // Bit 5 of the virtio-balloon features is VIRTIO_BALLOON_F_REPORTING
+    const REPORTING_DEVICES: &str =
+        "grep -l '^.....1' /sys/bus/virtio/drivers/virtio_balloon/virtio*/features";
+
+    if ushell.run(cmd!("test -e {}", ORDER_PARAM)).is_err() {
+        return Err(failure::format_err!(
+            "--page_reporting needs a kernel built with CONFIG_PAGE_REPORTING"
+        ));
+    }
+
+    if on {
+        ushell.run(cmd!("sudo modprobe virtio_balloon"))?;
+        if ushell.run(cmd!("{}", REPORTING_DEVICES)).is_err() {
+            return Err(failure::format_err!(
+                "No virtio-balloon device on the remote has free page reporting. \
+                 Enable it in the hypervisor, e.g. free-page-reporting=on for QEMU."
+            ));
+        }
+    } else if ushell
+        .run(cmd!("lsmod | grep -q '^virtio_balloon '"))
+        .is_ok()
+    {
+        ushell.run(cmd!("sudo modprobe -r virtio_balloon"))?;
+    } else if ushell.run(cmd!("{}", REPORTING_DEVICES)).is_ok() {
+        return Err(failure::format_err!(
+            "Free page reporting can't be turned off because virtio-balloon is built into \
+             the kernel"
+        ));
+    }
+
+    // For the log
+    ushell.run(cmd!("cat {}", ORDER_PARAM))?;
+
+    Ok(())
+}
+
+/// Load the given kernel module unless a previous run on this boot already loaded it.
+fn insmod_if_needed(ushell: &SshShell, module: &str, path: &str) -> Result<(), failure::Error> {
+    if ushell.run(cmd!("lsmod | grep -q '^{} '", module)).is_err() {
+        ushell.run(cmd!("sudo insmod {}", path))?;
+    }
+
+    Ok(())
+}
+
+/// Sum up the failed allocation counts in an MMFS stats file. Each counter is on its own line
+/// with the value last.
+fn count_alloc_failures(stats: &str) -> usize {
+    stats
+        .lines()
+        .filter(|line| line.to_lowercase().contains("fail"))
+        .filter_map(|line| {
+            line.split(|c: char| c.is_whitespace() || c == ':')
+                .last()
+                .and_then(|v| v.parse::<usize>().ok())
+        })
+        .sum()
+}
+
+/// The pages in use in the fast and slow tiers, from the contents of
+/// `/sys/fs/tieredmmfs/stats`. Each tier is on a line starting with "fast" or "slow" that has
+/// its total and free page counts, e.g. `fast total: 1024 free: 512`. Returns `None` if either
+/// tier is missing.
+fn parse_tier_pages(stats: &str) -> Option<(u64, u64)> {
+    let used = |tier: &str| {
+        stats.lines().find_map(|line| {
+            let line = line.to_lowercase();
+            let mut words = line
+                .split(|c: char| c.is_whitespace() || c == ':' || c == '=')
+                .filter(|w| !w.is_empty());
+            if !words.next()?.starts_with(tier) {
+                return None;
+            }
+
+            let (mut total, mut free, mut last) = (None, None, "");
+            for word in words {
+                match word.parse::<u64>() {
+                    Ok(n) if last.contains("total") => total = Some(n),
+                    Ok(n) if last.contains("free") => free = Some(n),
+                    Ok(_) => {}
+                    Err(_) => last = word,
+                }
+            }
+            Some(total?.saturating_sub(free?))
+        })
+    };
+
+    Some((used("fast")?, used("slow")?))
+}
+
+/// Turn the samples of the tier occupancy collector into `timestamp fast_pages slow_pages`
+/// lines. Each sample starts with a `== <timestamp>` line followed by the TieredMMFS stats.
+/// Samples taken before the stats were available are skipped.
+fn tier_occupancy_lines(raw: &str) -> Vec<String> {
+    raw.split("== ")
+        .filter_map(|sample| {
+            let (timestamp, stats) = sample.split_once('\n')?;
+            let (fast, slow) = parse_tier_pages(stats)?;
+            Some(format!("{} {} {}", timestamp.trim(), fast, slow))
+        })
+        .collect()
+}
+
+/// The pmem namespaces of the remote, as `ndctl list -N` describes them.
+pub(crate) fn list_pmem_namespaces(
+    ushell: &SshShell,
+) -> Result<Vec<serde_json::Value>, failure::Error> {
+    let namespaces = ushell.run(cmd!("sudo ndctl list -N"))?.stdout;
+    // ndctl prints nothing if there are no namespaces, and a single object instead of an
+    // array if there is only one
+    if namespaces.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match serde_json::from_str(&namespaces)? {
+        serde_json::Value::Array(namespaces) => Ok(namespaces),
+        namespace => Ok(vec![namespace]),
+    }
+}
+
+/// Make sure each of the given pmem block devices is backed by an fsdax namespace,
+/// reconfiguring any that are not, and record the resulting namespaces.
+fn ensure_fsdax_namespaces(
+    ushell: &SshShell,
+    devs: &[&str],
+    namespaces_file: &str,
+) -> Result<(), failure::Error> {
+    let namespaces = list_pmem_namespaces(ushell)?;
+
+    for dev in devs {
+        let namespace = namespaces
+            .iter()
+            .find(|ns| ns["blockdev"].as_str() == Some(*dev))
+            .ok_or_else(|| failure::format_err!("No pmem namespace found for /dev/{}", dev))?;
+        let mode = namespace["mode"].as_str().unwrap_or("unknown");
+
+        if mode != "fsdax" {
+            let name = namespace["dev"].as_str().ok_or_else(|| {
+                failure::format_err!("Unable to get the namespace name of /dev/{}", dev)
+            })?;
+            println!(
+                "/dev/{} ({}) is in {} mode. Reconfiguring it to fsdax...",
+                dev, name, mode
+            );
+            ushell.run(cmd!(
+                "sudo ndctl create-namespace --mode fsdax --force -e {}",
+                name
+            ))?;
+        }
+    }
+
+    ushell.run(cmd!("sudo ndctl list -N | tee {}", namespaces_file))?;
+
+    Ok(())
+}
+
+/// The number of YCSB records that fit in a memcached cache of `size` GB.
+fn memcached_record_count(
+    size: usize,
+    field_count: Option<usize>,
+    field_length: Option<usize>,
+) -> usize {
+    // Empirically, this is the amount of bytes a single record takes with the YCSB default
+    // of 10 fields of 100 bytes. The rest is key and memcached item overhead.
+    const DEFAULT_RECORD_SIZE: usize = 1350;
+    const RECORD_OVERHEAD: usize = DEFAULT_RECORD_SIZE - 10 * 100;
+    let record_size = field_count.unwrap_or(10) * field_length.unwrap_or(100) + RECORD_OVERHEAD;
+    // "size" is the size in GB on the cache, so take off a GB to add some wiggle room
+    (size.saturating_sub(1) << 30) / record_size
+}
+
+/// The YCSB properties that set a record layout other than the default 10 fields of 100 bytes.
+fn ycsb_layout_props(field_count: Option<usize>, field_length: Option<usize>) -> String {
+    let mut props = String::new();
+    if let Some(field_count) = field_count {
+        props.push_str(&format!(" -p fieldcount={}", field_count));
+    }
+    if let Some(field_length) = field_length {
+        props.push_str(&format!(" -p fieldlength={}", field_length));
+    }
+    props
+}
+
+/// A `taskset` prefix that pins the YCSB client to `client_cores`, or nothing if there are none.
+fn ycsb_client_taskset(client_cores: &[usize]) -> String {
+    if client_cores.is_empty() {
+        String::new()
+    } else {
+        let cores = client_cores
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("taskset -c {} ", cores)
+    }
+}
+
+/// Start a memcached server with a `server_size_mb` MB cache pinned to `pin_core`, and wait
+/// for it to accept connections.
+fn start_memcached(
+    ushell: &SshShell,
+    memcached_dir: &str,
+    user: &str,
+    server_size_mb: usize,
+    hugepages: bool,
+    cmd_prefix: &str,
+    pin_core: usize,
+) -> Result<(), failure::Error> {
+    ushell.run(cmd!(
+        "taskset -c {} {}{}/memcached {} -m {} -d -u {}",
+        pin_core,
+        cmd_prefix,
+        memcached_dir,
+        if hugepages { "-L" } else { "" },
+        server_size_mb,
+        user
+    ))?;
+
+    while ushell
+        .run(cmd!(
+            "{}/scripts/memcached-tool localhost:11211",
+            memcached_dir
+        ))
+        .is_err()
+    {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+
+    Ok(())
+}
+
+/// Run the YCSB load phase against a running memcached server with the given record layout.
+fn load_ycsb_memcached(
+    ushell: &SshShell,
+    ycsb_dir: &str,
+    record_count: usize,
+    field_count: Option<usize>,
+    field_length: Option<usize>,
+    client_cores: &[usize],
+) -> Result<(), failure::Error> {
+    ushell.run(
+        cmd!(
+            "{}./bin/ycsb load memcached -s \
+            -p workload=site.ycsb.workloads.CoreWorkload \
+            -p recordcount={} -p memcached.hosts=localhost:11211{}",
+            ycsb_client_taskset(&client_cores[..client_cores.len().min(1)]),
+            record_count,
+            ycsb_layout_props(field_count, field_length)
+        )
+        .cwd(ycsb_dir),
+    )?;
+
+    Ok(())
+}
+
+/// Like `memcached_record_count`, but an error that names `server` if no records fit.
+fn checked_record_count(
+    server: &str,
+    size: usize,
+    field_count: Option<usize>,
+    field_length: Option<usize>,
+) -> Result<usize, failure::Error> {
+    match memcached_record_count(size, field_count, field_length) {
+        0 if size < 2 => Err(failure::format_err!(
+            "A {} SIZE of {} GB leaves no room for records. It must be at least 2.",
+            server,
+            size
+        )),
+        0 => Err(failure::format_err!(
+            "A {} SIZE of {} GB is too small for a single record of the YCSB record layout.",
+            server,
+            size
+        )),
+        record_count => Ok(record_count),
+    }
+}
+
+/// Run only the YCSB run phase against a memcached server that was already started and
+/// loaded, either by a previous `--load_only` run or by `load_ycsb_memcached`.
+fn run_ycsb_preloaded_memcached(
+    ushell: &SshShell,
+    ycsb_dir: &str,
+    record_count: usize,
+    op_count: usize,
+    read_prop: f32,
+    update_prop: f32,
+    field_count: Option<usize>,
+    field_length: Option<usize>,
+    ycsb_file: &str,
+    client_cores: &[usize],
+    threads: Option<usize>,
+) -> Result<(), failure::Error> {
+    let taskset = ycsb_client_taskset(client_cores);
+    let mut extra_props = String::new();
+    if let Some(threads) = threads {
+        extra_props.push_str(&format!(" -threads {}", threads));
+    }
+    extra_props.push_str(&ycsb_layout_props(field_count, field_length));
+
+    ushell.run(
+        cmd!(
+            "{}./bin/ycsb run memcached -s \
+            -p workload=site.ycsb.workloads.CoreWorkload \
+            -p recordcount={} -p operationcount={} \
+            -p readproportion={} -p updateproportion={} -p insertproportion={} \
+            -p requestdistribution=zipfian -p memcached.hosts=localhost:11211{} | tee {}",
+            taskset,
+            record_count,
+            op_count,
+            read_prop,
+            update_prop,
+            1.0 - read_prop - update_prop,
+            extra_props,
+            ycsb_file
+        )
+        .cwd(ycsb_dir),
+    )?;
+
+    Ok(())
+}
+
+fn run_alloc_test(
+    ushell: &SshShell,
+    bmks_dir: &str,
+    size: usize,
+    num_allocs: usize,
+    threads: usize,
+    cmd_prefix: Option<&str>,
+    alloc_test_file: &str,
+    runtime_file: &str,
+    pin_cores_str: &str,
+    use_map_populate: bool,
+    touch_pages: bool,
+) -> Result<(), failure::Error> {
+    // alloc_test uses MAP_POPULATE if it has a fourth arg
+    let populate_arg = if use_map_populate {
+        "populate"
+    } else if touch_pages {
+        "t"
+    } else {
+        ""
+    };
+
+    let start = Instant::now();
+    ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} {}./alloc_test {} {} {} {} | sudo tee {}",
+            pin_cores_str,
+            cmd_prefix.unwrap_or(""),
+            record_pid_prefix(),
+            size,
+            num_allocs,
+            threads,
+            populate_arg,
+            alloc_test_file
+        )
+        .cwd(bmks_dir),
+    )?;
+    let duration = Instant::now() - start;
+
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+    Ok(())
+}
+
+/// `libscail::workloads::run_spec17` only runs some of the speed benchmarks, so the rest and
+/// the rate runs go through `runcpu` with a config that pins them to `pin_cores`. With
+/// `copies`, this runs the rate benchmark with each copy bound to its own core.
+fn run_spec17_runcpu(
+    ushell: &SshShell,
+    spec_dir: &str,
+    benchmark: &str,
+    copies: Option<usize>,
+    tune: &str,
+    cmd_prefix: Option<&str>,
+    spec_copies_file: &str,
+    runtime_file: &str,
+    pin_cores: &[usize],
+) -> Result<(), failure::Error> {
+    let config = dir!(spec_dir, "config/spec-linux-x86.cfg");
+    let runner_config = dir!(spec_dir, "config/spec-linux-x86-runner.cfg");
+    let (pinning, parallel_arg) = if let Some(copies) = copies {
+        let bind = pin_cores
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        (
+            format!("   bind = {}\\n   submit = taskset -c $BIND", bind),
+            format!("--copies={}", copies),
+        )
+    } else {
+        let cores = pin_cores
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        (
+            format!("   submit = taskset -c {}", cores),
+            format!("--threads={}", pin_cores.len()),
+        )
+    };
+    ushell.run(cmd!("cp {} {}", config, runner_config))?;
+    ushell.run(cmd!(
+        "printf '\\ndefault:\\n{} {}$command\\n' >> {}",
+        pinning,
+        cmd_prefix.unwrap_or(""),
+        runner_config
+    ))?;
+
+    let start = Instant::now();
+    ushell.run(
+        cmd!(
+            "source shrc && runcpu --config=spec-linux-x86-runner.cfg --action=run \
+             --noreportable --iterations=1 --size=ref --tune={} {} {}",
+            tune,
+            parallel_arg,
+            benchmark
+        )
+        .cwd(spec_dir),
+    )?;
+    let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    // runcpu logs when each copy finished, which shows how unevenly the copies ran
+    if copies.is_some() {
+        ushell.run(cmd!(
+            "grep -h 'Copy [0-9]* of {}.*finished' `ls -t {}/result/CPU2017.*.log | head -n1` \
+             | tee {}",
+            benchmark,
+            spec_dir,
+            spec_copies_file
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// `libscail::workloads::run_canneal` only pins to a single core, so multi-threaded runs go
+/// through `parsecmgmt` directly.
+fn run_canneal_threaded(
+    ushell: &SshShell,
+    parsec_dir: &str,
+    workload: CannealWorkload,
+    threads: usize,
+    cmd_prefix: Option<&str>,
+    runtime_file: &str,
+    pin_cores_str: &str,
+) -> Result<(), failure::Error> {
+    let input = match workload {
+        CannealWorkload::Small => "simsmall",
+        CannealWorkload::Medium => "simmedium",
+        CannealWorkload::Large => "simlarge",
+        CannealWorkload::Native => "native",
+    };
+
+    // parsecmgmt runs the benchmark binary by appending it to the submit command
+    let submit = format!(
+        "sudo taskset -c {} {}",
+        pin_cores_str,
+        cmd_prefix.unwrap_or("")
+    );
+
+    let start = Instant::now();
+    ushell.run(
+        cmd!(
+            "./bin/parsecmgmt -a run -p canneal -i {} -n {} -s {}",
+            input,
+            threads,
+            escape_for_bash(&submit),
+        )
+        .cwd(parsec_dir),
+    )?;
+    let duration = Instant::now() - start;
+
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+    Ok(())
+}
+
+fn run_gups(
+    ushell: &SshShell,
+    gups_dir: &str,
+    threads: usize,
+    exp: usize,
+    variant: GupsVariant,
+    hot_exp: Option<usize>,
+    move_hot: bool,
+    num_updates: usize,
+    cmd_prefix: Option<&str>,
+    gups_file: &str,
+    gups_metric_file: &str,
+    runtime_file: &str,
+    pin_cores_str: &str,
+) -> Result<(), failure::Error> {
+    let start = Instant::now();
+
+    let output = ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} {}{} | tee {}",
+            pin_cores_str,
+            cmd_prefix.unwrap_or(""),
+            record_pid_prefix(),
+            gups_command(threads, exp, variant, hot_exp, move_hot, num_updates),
+            gups_file,
+        )
+        .cwd(gups_dir),
+    )?;
+    let duration = Instant::now() - start;
+
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    // Pull out the throughput so runs can be compared without digging through the output
+    let mut metrics = Vec::new();
+    if let Some(gups) = output
+        .stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("GUPS = "))
+    {
+        metrics.push(format!("gups {}", gups.trim()));
+    }
+    if let GupsVariant::Hotset = variant {
+        // gups-hotset-move records the GUPS of every second in tot_gups.txt
+        let per_second = ushell
+            .run(cmd!("cat tot_gups.txt").cwd(gups_dir))?
+            .stdout
+            .lines()
+            .filter_map(|line| line.trim().parse::<f64>().ok())
+            .collect::<Vec<_>>();
+        let (before, after) = per_second.split_at(HOTSET_MOVE_SECOND.min(per_second.len()));
+        let mean = |gups: &[f64]| gups.iter().sum::<f64>() / gups.len() as f64;
+        if !before.is_empty() {
+            metrics.push(format!("gups_pre_move {:.10}", mean(before)));
+        }
+        if move_hot && !after.is_empty() {
+            metrics.push(format!("gups_post_move {:.10}", mean(after)));
+        }
+    }
+    ushell.run(cmd!(
+        "echo {} | tee {}",
+        escape_for_bash(&metrics.join("\n")),
+        gups_metric_file
+    ))?;
+
+    Ok(())
+}
+
+/// The number of updates GUPS does by default for a table of 2^`exp` entries.
+fn default_gups_updates(exp: usize) -> usize {
+    (1 << exp) / 8
+}
+
+/// The command to run GUPS with from its directory.
+fn gups_command(
+    threads: usize,
+    exp: usize,
+    variant: GupsVariant,
+    hot_exp: Option<usize>,
+    move_hot: bool,
+    num_updates: usize,
+) -> String {
+    match variant {
+        GupsVariant::Hotset => format!(
+            "./gups-hotset-move {} {} {} 8 {} {}",
+            threads,
+            num_updates,
+            exp,
+            hot_exp.unwrap_or(0),
+            if move_hot { 1 } else { 0 },
+        ),
+        GupsVariant::Basic => format!("./gups {} {} {} 8", threads, num_updates, exp),
+    }
+}
+
+/// The ids of the NUMA nodes on the remote, as reported by `numactl --hardware`.
+fn get_numa_nodes(ushell: &SshShell) -> Result<Vec<u32>, failure::Error> {
+    let hardware = ushell.run(cmd!("numactl --hardware"))?.stdout;
+    Ok(parse_numa_nodes(&hardware))
+}
+
+/// Parse the NUMA node ids out of the output of `numactl --hardware`.
+fn parse_numa_nodes(hardware: &str) -> Vec<u32> {
+    // Each node has a line like "node 0 cpus: 0 1 2 3", with no cpus for memory-only nodes
+    hardware
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some("node"), Some(nid), Some("cpus:")) => nid.parse::<u32>().ok(),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Get `cores_per_node` cores from each NUMA node of the remote, skipping hyperthreads.
+fn get_node_cores(
+    ushell: &SshShell,
+    cores_per_node: usize,
+) -> Result<Vec<(usize, Vec<usize>)>, failure::Error> {
+    let lscpu = ushell.run(cmd!("lscpu -p=CPU,CORE,NODE"))?.stdout;
+    let mut node_cores = std::collections::BTreeMap::<usize, Vec<usize>>::new();
+    let mut seen_cores = std::collections::BTreeSet::new();
+
+    for line in lscpu.lines().filter(|line| !line.starts_with('#')) {
+        let fields = line
+            .split(',')
+            .map(|f| f.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let (cpu, core, node) = (fields[0], fields[1], fields[2]);
+
+        // Only use the first hyperthread of each core
+        if seen_cores.insert(core) {
+            node_cores.entry(node).or_default().push(cpu);
+        }
+    }
+
+    node_cores
+        .into_iter()
+        .map(|(node, cores)| {
+            if cores.len() < cores_per_node {
+                Err(failure::format_err!(
+                    "NUMA node {} only has {} cores, but {} are needed",
+                    node,
+                    cores.len(),
+                    cores_per_node
+                ))
+            } else {
+                Ok((node, cores[..cores_per_node].to_vec()))
+            }
+        })
+        .collect()
+}
+
+/// Run a copy of `bmk_cmd` on each NUMA node at the same time, with the output of each going
+/// to `output_file` suffixed with the node id.
+fn run_instances_per_node(
+    ushell: &SshShell,
+    node_cores: &[(usize, Vec<usize>)],
+    bmk_dir: &str,
+    bmk_cmd: &str,
+    cmd_prefix: Option<&str>,
+    output_file: &str,
+    runtime_file: &str,
+) -> Result<(), failure::Error> {
+    let start = Instant::now();
+
+    let mut handles = Vec::new();
+    for (node, cores) in node_cores {
+        let cores_str = cores
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        handles.push(
+            ushell.spawn(
+                cmd!(
+                    "sudo numactl --cpunodebind={0} --membind={0} taskset -c {1} {2} {3} \
+                    | tee {4}.node{0}",
+                    node,
+                    cores_str,
+                    cmd_prefix.unwrap_or(""),
+                    bmk_cmd,
+                    output_file,
+                )
+                .cwd(bmk_dir),
+            )?,
+        );
+    }
+    for handle in handles {
+        handle.join().1?;
+    }
+
+    let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    Ok(())
+}
+
+fn run_pagewalk_coherence(
+    ushell: &SshShell,
+    coherence_dir: &str,
+    mode: PagewalkCoherenceMode,
+    cmd_prefix: Option<&str>,
+    coherence_file: &str,
+    runtime_file: &str,
+    pin_core: usize,
+) -> Result<(), failure::Error> {
+    // Building this ubmks requires the kernel to be built, so we build it now
+    // instead of during setup
+    ushell.run(cmd!("make").cwd(coherence_dir))?;
+    insmod_if_needed(ushell, "pgmod", &dir!(coherence_dir, "pgmod.ko"))?;
+
+    let start = Instant::now();
+    ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} ./paging --mode {} | tee {}",
+            pin_core,
+            cmd_prefix.unwrap_or(""),
+            match mode {
+                PagewalkCoherenceMode::Speculation => 0,
+                PagewalkCoherenceMode::Coherence => 1,
+            },
+            coherence_file,
+        )
+        .cwd(coherence_dir),
+    )?;
+    let duration = Instant::now() - start;
+
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    Ok(())
+}
+
+fn run_rocksdb(
+    ushell: &SshShell,
+    rocksdb_dir: &str,
+    db_dir: &str,
+    benchmarks: &str,
+    num: usize,
+    value_size: usize,
+    cmd_prefix: Option<&str>,
+    rocksdb_file: &str,
+    runtime_file: &str,
+    pin_core: usize,
+) -> Result<(), failure::Error> {
+    ushell.run(cmd!("sudo rm -rf {}", db_dir))?;
+
+    let start = Instant::now();
+
+    let output = ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} {}./db_bench --benchmarks={} --num={} --value_size={} --db={}",
+            pin_core,
+            cmd_prefix.unwrap_or(""),
+            record_pid_prefix(),
+            benchmarks,
+            num,
+            value_size,
+            db_dir
+        )
+        .cwd(rocksdb_dir),
+    )?;
+
+    let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    // db_bench prints a line like
+    // "fillrandom   :       3.456 micros/op 289352 ops/sec;   32.0 MB/s" for each benchmark
+    let summary = output
+        .stdout
+        .lines()
+        .filter(|line| line.contains("micros/op"))
+        .collect::<Vec<_>>();
+    ushell.run(cmd!(
+        "echo {} | tee {}",
+        escape_for_bash(&summary.join("\n")),
+        rocksdb_file
+    ))?;
+
+    ushell.run(cmd!("sudo rm -rf {}", db_dir))?;
+
+    Ok(())
+}
+
+fn run_graph500(
+    ushell: &SshShell,
+    graph500_dir: &str,
+    size: usize,
+    cmd_prefix: Option<&str>,
+    graph500_file: &str,
+    runtime_file: &str,
+    pin_core: usize,
+) -> Result<(), failure::Error> {
+    let start = Instant::now();
+
+    ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} ./graph500_reference_bfs_sssp {} | tee {}",
+            pin_core,
+            cmd_prefix.unwrap_or(""),
+            size,
+            graph500_file
+        )
+        .cwd(graph500_dir),
+    )?;
+
+    let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    Ok(())
+}
+
+fn run_stream(
+    ushell: &SshShell,
+    bmks_dir: &str,
+    cmd_prefix: Option<&str>,
+    stream_file: &str,
+    runtime_file: &str,
+    pin_cores_str: &str,
+) -> Result<(), failure::Error> {
+    let start = Instant::now();
 
-    dump_sys_info(&ushell)?;
+    ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} {}./stream | tee {}",
+            pin_cores_str,
+            cmd_prefix.unwrap_or(""),
+            record_pid_prefix(),
+            stream_file
+        )
+        .cwd(bmks_dir),
+    )?;
 
-    ushell.run(cmd!(
-        "sudo LD_LIBRARY_PATH=/usr/lib64/ cpupower frequency-set -g performance",
-    ))?;
-    ushell.run(cmd!("lscpu"))?;
-    set_kernel_printk_level(&ushell, 5)?;
+    let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
 
-    Ok(ushell)
+    Ok(())
 }
 
-fn run_alloc_test(
+fn run_fileread(
     ushell: &SshShell,
     bmks_dir: &str,
-    size: usize,
-    num_allocs: usize,
+    file_dir: &str,
+    file_size_gb: usize,
     threads: usize,
+    pattern: FileReadPattern,
     cmd_prefix: Option<&str>,
-    alloc_test_file: &str,
+    file_read_file: &str,
     runtime_file: &str,
     pin_cores_str: &str,
-    use_map_populate: bool,
-    touch_pages: bool,
 ) -> Result<(), failure::Error> {
-    // alloc_test uses MAP_POPULATE if it has a fourth arg
-    let populate_arg = if use_map_populate {
-        "populate"
-    } else if touch_pages {
-        "t"
-    } else {
-        ""
-    };
-
+    let file = dir!(file_dir, "file_read.dat");
     let start = Instant::now();
+
     ushell.run(
         cmd!(
-            "sudo taskset -c {} {} ./alloc_test {} {} {} {} | sudo tee {}",
+            "sudo taskset -c {} {} {}./file_read {} {} {} {} | tee {}",
             pin_cores_str,
             cmd_prefix.unwrap_or(""),
-            size,
-            num_allocs,
+            record_pid_prefix(),
+            file,
+            file_size_gb,
             threads,
-            populate_arg,
-            alloc_test_file
+            match pattern {
+                FileReadPattern::Sequential => "seq",
+                FileReadPattern::Random => "rand",
+            },
+            file_read_file
+        )
+        .cwd(bmks_dir),
+    )?;
+
+    let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+    ushell.run(cmd!("sudo rm -f {}", file))?;
+
+    Ok(())
+}
+
+fn run_cachebench(
+    ushell: &SshShell,
+    bmks_dir: &str,
+    max_size: usize,
+    stride: usize,
+    cmd_prefix: Option<&str>,
+    cachebench_file: &str,
+    runtime_file: &str,
+    pin_core: usize,
+) -> Result<(), failure::Error> {
+    let start = Instant::now();
+
+    // Each line of the output is a working set size and its latency
+    ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} {}./cachebench {} {} | tee {}",
+            pin_core,
+            cmd_prefix.unwrap_or(""),
+            record_pid_prefix(),
+            max_size,
+            stride,
+            cachebench_file
         )
         .cwd(bmks_dir),
     )?;
+
     let duration = Instant::now() - start;
+    ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    Ok(())
+}
+
+fn run_idle(
+    ushell: &SshShell,
+    duration: usize,
+    cmd_prefix: Option<&str>,
+    runtime_file: &str,
+    pin_core: usize,
+) -> Result<(), failure::Error> {
+    let start = Instant::now();
+
+    ushell.run(cmd!(
+        "sudo taskset -c {} {} {}sleep {}",
+        pin_core,
+        cmd_prefix.unwrap_or(""),
+        record_pid_prefix(),
+        duration
+    ))?;
 
+    let duration = Instant::now() - start;
     ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
     Ok(())
 }
 
-fn run_gups(
+fn run_xsbench(
     ushell: &SshShell,
-    gups_dir: &str,
+    xsbench_dir: &str,
+    size: &str,
+    lookups: usize,
     threads: usize,
-    exp: usize,
-    hot_exp: Option<usize>,
-    move_hot: bool,
-    num_updates: usize,
     cmd_prefix: Option<&str>,
-    gups_file: &str,
+    xsbench_file: &str,
     runtime_file: &str,
     pin_cores_str: &str,
 ) -> Result<(), failure::Error> {
     let start = Instant::now();
 
-    if let Some(hot_exp) = hot_exp {
-        ushell.run(
-            cmd!(
-                "sudo taskset -c {} {} ./gups-hotset-move {} {} {} 8 {} {} | tee {}",
-                pin_cores_str,
-                cmd_prefix.unwrap_or(""),
-                threads,
-                num_updates,
-                exp,
-                hot_exp,
-                if move_hot { 1 } else { 0 },
-                gups_file,
-            )
-            .cwd(gups_dir),
-        )?;
-    } else {
-        ushell.run(
-            cmd!(
-                "sudo taskset -c {} {} ./gups {} {} {} 8 | tee {}",
-                pin_cores_str,
-                cmd_prefix.unwrap_or(""),
-                threads,
-                num_updates,
-                exp,
-                gups_file,
-            )
-            .cwd(gups_dir),
-        )?;
-    }
-    let duration = Instant::now() - start;
+    let output = ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} {}./XSBench -s {} -l {} -t {}",
+            pin_cores_str,
+            cmd_prefix.unwrap_or(""),
+            record_pid_prefix(),
+            size,
+            lookups,
+            threads
+        )
+        .cwd(xsbench_dir),
+    )?;
 
+    let duration = Instant::now() - start;
     ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
+
+    // XSBench reports lines like "Runtime:     12.345 seconds" and "Lookups/s:   1,234,567"
+    let results = output
+        .stdout
+        .lines()
+        .filter(|line| line.starts_with("Runtime:") || line.starts_with("Lookups/s:"))
+        .collect::<Vec<_>>();
+    ushell.run(cmd!(
+        "echo {} | tee {}",
+        escape_for_bash(&results.join("\n")),
+        xsbench_file
+    ))?;
+
     Ok(())
 }
 
-fn run_pagewalk_coherence(
+fn run_multichase(
     ushell: &SshShell,
-    coherence_dir: &str,
-    mode: PagewalkCoherenceMode,
+    bmks_dir: &str,
+    mem_size: usize,
+    chase: &str,
     cmd_prefix: Option<&str>,
-    coherence_file: &str,
+    multichase_file: &str,
     runtime_file: &str,
     pin_core: usize,
 ) -> Result<(), failure::Error> {
-    // Building this ubmks requires the kernel to be built, so we build it now
-    // instead of during setup
-    ushell.run(cmd!("make").cwd(coherence_dir))?;
-    ushell.run(cmd!("sudo insmod ./pgmod.ko").cwd(coherence_dir))?;
-
     let start = Instant::now();
+
+    // multichase prints the best ns per access of its samples
     ushell.run(
         cmd!(
-            "sudo taskset -c {} {} ./paging --mode {} | tee {}",
+            "sudo taskset -c {} {} {}./multichase -m {}m -c {} | tee {}",
             pin_core,
             cmd_prefix.unwrap_or(""),
-            match mode {
-                PagewalkCoherenceMode::Speculation => 0,
-                PagewalkCoherenceMode::Coherence => 1,
-            },
-            coherence_file,
+            record_pid_prefix(),
+            mem_size,
+            chase,
+            multichase_file
         )
-        .cwd(coherence_dir),
+        .cwd(dir!(bmks_dir, "multichase")),
     )?;
-    let duration = Instant::now() - start;
 
+    let duration = Instant::now() - start;
     ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
 
     Ok(())
 }
 
-fn run_graph500(
+/// Read the total and local memory bandwidth byte counts of a resctrl monitoring group, summed
+/// over all of the L3 domains.
+fn read_mbm(ushell: &SshShell, group: &str) -> Result<(u64, u64), failure::Error> {
+    let mut counts = [0; 2];
+    for (count, file) in counts
+        .iter_mut()
+        .zip(["mbm_total_bytes", "mbm_local_bytes"])
+    {
+        let values = ushell
+            .run(cmd!("sudo cat {}/mon_data/mon_L3_*/{}", group, file).use_bash())?
+            .stdout;
+        // Counters that are not available read as "Unavailable"
+        *count = values
+            .lines()
+            .filter_map(|value| value.trim().parse::<u64>().ok())
+            .sum();
+    }
+
+    Ok((counts[0], counts[1]))
+}
+
+/// Read the package and DRAM energy counters of the first RAPL package as
+/// (domain name, energy in uJ, max energy in uJ). Returns `None` if there is no RAPL powercap.
+fn read_rapl(ushell: &SshShell) -> Result<Option<Vec<(String, u64, u64)>>, failure::Error> {
+    const RAPL_DIR: &str = "/sys/class/powercap/intel-rapl:0";
+    if ushell.run(cmd!("test -e {}/energy_uj", RAPL_DIR)).is_err() {
+        return Ok(None);
+    }
+
+    // The package domain, followed by its subdomains, one of which is usually the DRAM
+    let domains = ushell
+        .run(
+            cmd!(
+                "for d in {0} {0}/intel-rapl:0:*; do \
+                    [ -e $d/energy_uj ] && echo `cat $d/name` `sudo cat $d/energy_uj` \
+                    `cat $d/max_energy_range_uj`; \
+                done; true",
+                RAPL_DIR
+            )
+            .use_bash(),
+        )?
+        .stdout;
+
+    let domains = domains
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            match fields.as_slice() {
+                [name, energy, max] if name.starts_with("package") || *name == "dram" => {
+                    Some((name.to_string(), energy.parse().ok()?, max.parse().ok()?))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    Ok(Some(domains))
+}
+
+/// A bash expression for the PID of the process the collectors should follow. This is the PID
+/// the runner recorded when it launched the workload, if it did. Otherwise, it is the process
+/// named `proc_name` with the largest RSS, since servers and SPEC rate runs have several.
+fn workload_pid_cmd(proc_name: &str) -> String {
+    format!(
+        "$(cat {} 2>/dev/null || (pgrep -x {} | paste -sd, \
+        | xargs -r ps -o pid= --sort=-rss -p | head -n1 | tr -d ' '))",
+        WORKLOAD_PID_FILE, proc_name
+    )
+}
+
+/// A prefix for the command of a workload that records its PID in `WORKLOAD_PID_FILE` for the
+/// collectors. It has to come right before the binary, after any other prefixes.
+fn record_pid_prefix() -> String {
+    format!(
+        "sh -c 'echo $$ > {}; exec \"$0\" \"$@\"' ",
+        WORKLOAD_PID_FILE
+    )
+}
+
+fn run_custom(
     ushell: &SshShell,
-    graph500_dir: &str,
-    size: usize,
+    binary: &str,
+    args: &[String],
+    cwd: &str,
     cmd_prefix: Option<&str>,
-    graph500_file: &str,
+    custom_file: &str,
     runtime_file: &str,
-    pin_core: usize,
+    pin_cores_str: &str,
 ) -> Result<(), failure::Error> {
+    let args = args
+        .iter()
+        .map(|arg| escape_for_bash(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
     let start = Instant::now();
 
     ushell.run(
         cmd!(
-            "sudo taskset -c {} {} ./graph500_reference_bfs_sssp {} | tee {}",
-            pin_core,
+            "sudo taskset -c {} {} {}{} {} | tee {}",
+            pin_cores_str,
             cmd_prefix.unwrap_or(""),
-            size,
-            graph500_file
+            record_pid_prefix(),
+            binary,
+            args,
+            custom_file
         )
-        .cwd(graph500_dir),
+        .cwd(cwd),
     )?;
 
     let duration = Instant::now() - start;
@@ -1679,28 +6129,287 @@ fn run_graph500(
     Ok(())
 }
 
-fn run_stream(
+fn run_gap(
     ushell: &SshShell,
-    bmks_dir: &str,
+    gap_dir: &str,
+    graph_dir: &str,
+    kernel: GapKernel,
+    scale: usize,
+    threads: usize,
+    trials: Option<usize>,
     cmd_prefix: Option<&str>,
-    stream_file: &str,
+    gap_file: &str,
+    gap_trials_file: &str,
     runtime_file: &str,
     pin_cores_str: &str,
 ) -> Result<(), failure::Error> {
-    let start = Instant::now();
+    let (graph, out_flag) = if kernel.weighted() {
+        (dir!(graph_dir, format!("kron{}.wsg", scale)), "-wb")
+    } else {
+        (dir!(graph_dir, format!("kron{}.sg", scale)), "-b")
+    };
 
+    // Generating the graph isn't part of the workload, so do it first
     ushell.run(
         cmd!(
-            "sudo taskset -c {} {} ./stream | tee {}",
+            "taskset -c {} env OMP_NUM_THREADS={} ./converter -g{} -k16 {} {}",
+            pin_cores_str,
+            threads,
+            scale,
+            out_flag,
+            graph
+        )
+        .cwd(gap_dir),
+    )?;
+
+    let trials_arg = trials.map(|n| format!("-n {}", n)).unwrap_or_default();
+
+    let start = Instant::now();
+
+    let output = ushell.run(
+        cmd!(
+            "sudo taskset -c {} {} env OMP_NUM_THREADS={} {}./{} -f {} {} | tee {}",
             pin_cores_str,
             cmd_prefix.unwrap_or(""),
-            stream_file
+            threads,
+            record_pid_prefix(),
+            kernel.binary(),
+            graph,
+            trials_arg,
+            gap_file
         )
-        .cwd(bmks_dir),
+        .cwd(gap_dir),
     )?;
 
     let duration = Instant::now() - start;
     ushell.run(cmd!("echo {} > {}", duration.as_millis(), runtime_file))?;
 
+    // GAP prints a line like "Trial Time:          0.12345" for each trial
+    let trials = output
+        .stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Trial Time:"))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    ushell.run(cmd!(
+        "echo {} | tee {}",
+        escape_for_bash(&trials.join("\n")),
+        gap_trials_file
+    ))?;
+
+    ushell.run(cmd!("rm -f {}", graph))?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start: usize, size: usize) -> MemRegion {
+        MemRegion { size, start }
+    }
+
+    fn config(args: &str) -> Config {
+        let matches = cli_options()
+            .get_matches_from_safe(std::iter::once("fbmm_exp").chain(args.split_whitespace()))
+            .unwrap();
+        parse_config(&matches).unwrap()
+    }
+
+    #[test]
+    fn default_gups_updates_is_an_eighth_of_the_table() {
+        assert_eq!(default_gups_updates(2), 0);
+        assert_eq!(default_gups_updates(3), 1);
+        assert_eq!(default_gups_updates(10), 128);
+        assert_eq!(default_gups_updates(32), 1 << 29);
+    }
+
+    #[test]
+    fn grub_cmds_strip_old_options() {
+        use std::io::Write;
+
+        // Run the stripping pipeline on a sample grub config instead of the real one
+        let strip = grub_cmds(&[])[0]
+            .replace("/etc/default/grub", "/dev/stdin")
+            .replace("sudo tee /tmp/grub", "cat");
+        let mut sh = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&strip)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        sh.stdin
+            .take()
+            .unwrap()
+            .write_all(
+                b"GRUB_CMDLINE_LINUX=\"quiet memmap=4G!8G memmap=16G!12G do_tpp maxcpus=8 \
+                  splash\"\n",
+            )
+            .unwrap();
+        let output = sh.wait_with_output().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "GRUB_CMDLINE_LINUX=\"quiet splash\"\n"
+        );
+    }
+
+    #[test]
+    fn workload_pid_prefers_the_recorded_pid() {
+        let pid = workload_pid_cmd("memcached");
+        // The recorded PID comes first, with the largest matching process as the fallback
+        let recorded = pid.find(WORKLOAD_PID_FILE).unwrap();
+        let fallback = pid.find("pgrep -x memcached").unwrap();
+        assert!(recorded < fallback);
+        assert!(pid.contains("--sort=-rss"));
+        assert!(pid.contains("head -n1"));
+    }
+
+    #[test]
+    fn record_pid_prefix_writes_the_pid_file() {
+        let prefix = record_pid_prefix();
+        assert!(prefix.contains(&format!("echo $$ > {}", WORKLOAD_PID_FILE)));
+        // The workload must replace the shell so the recorded PID is its own
+        assert!(prefix.contains("exec \"$0\" \"$@\""));
+        assert!(prefix.ends_with(' '));
+    }
+
+    #[test]
+    fn batch_keeps_the_loaded_memcached() {
+        let load = config("host:22 user memcached 4 --load_only");
+        let reuse = config("host:22 user memcached 4 --reuse_load");
+        assert!(!tear_down_between(&load, Some(&reuse)));
+        // The loaded server is only there for later --reuse_load runs
+        assert!(!tear_down_between(&load, Some(&load)));
+        assert!(!tear_down_between(&reuse, Some(&reuse)));
+    }
+
+    #[test]
+    fn batch_keeps_a_reused_mount() {
+        let first = config("host:22 user --fbmm --ext4 alloctest 1");
+        let reuse = config("host:22 user --fbmm --ext4 --reuse_mount alloctest 1");
+        assert!(!tear_down_between(&first, Some(&reuse)));
+        assert!(tear_down_between(&reuse, Some(&first)));
+    }
+
+    #[test]
+    fn batch_tears_down_between_plain_runs() {
+        let run = config("host:22 user alloctest 1");
+        assert!(tear_down_between(&run, Some(&run)));
+        assert!(!tear_down_between(&run, None));
+    }
+
+    #[test]
+    fn record_count_needs_a_size_of_two() {
+        assert!(checked_record_count("memcached", 0, None, None).is_err());
+        assert!(checked_record_count("memcached", 1, None, None).is_err());
+        assert!(checked_record_count("memcached", 2, None, None).unwrap() > 0);
+    }
+
+    #[test]
+    fn record_count_fits_the_record_layout() {
+        assert!(checked_record_count("memcached", 2, Some(1), Some(1 << 31)).is_err());
+        assert_eq!(
+            checked_record_count("memcached", 2, Some(1), Some(1000)).unwrap(),
+            memcached_record_count(2, Some(1), Some(1000))
+        );
+    }
+
+    #[test]
+    fn parse_good_node_weight() {
+        let weight = parse_node_weight("0:1").unwrap();
+        assert_eq!((weight.nid, weight.weight), (0, 1));
+    }
+
+    #[test]
+    fn parse_bad_node_weights() {
+        for s in &["0", "0:abc", "a:1", "0:1:2", ":1", "0:", "-1:1"] {
+            assert!(parse_node_weight(s).is_err(), "{} should not parse", s);
+        }
+    }
+
+    #[test]
+    fn parse_node_weights_rejects_duplicate_nodes() {
+        assert_eq!(
+            parse_node_weights(vec!["0:1", "1:3"].into_iter())
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(parse_node_weights(vec!["0:1", "1:3", "0:2"].into_iter()).is_err());
+    }
+
+    #[test]
+    fn parse_numa_nodes_with_cpuless_node() {
+        let hardware = "available: 3 nodes (0-2)\n\
+                        node 0 cpus: 0 1 2 3\n\
+                        node 0 size: 64000 MB\n\
+                        node 0 free: 60000 MB\n\
+                        node 1 cpus: 4 5 6 7\n\
+                        node 1 size: 64000 MB\n\
+                        node 2 cpus:\n\
+                        node 2 size: 128000 MB\n\
+                        node distances:\n\
+                        node   0   1   2\n\
+                          0:  10  21  17\n";
+        assert_eq!(parse_numa_nodes(hardware), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn parse_numa_nodes_without_nodes() {
+        assert!(parse_numa_nodes("No NUMA available on this system\n").is_empty());
+    }
+
+    #[test]
+    fn disjoint_regions_do_not_overlap() {
+        assert!(!region(0, 4).overlaps(&region(8, 4)));
+        assert!(!region(8, 4).overlaps(&region(0, 4)));
+    }
+
+    #[test]
+    fn touching_regions_do_not_overlap() {
+        assert!(!region(0, 4).overlaps(&region(4, 4)));
+        assert!(!region(4, 4).overlaps(&region(0, 4)));
+    }
+
+    #[test]
+    fn contained_regions_overlap() {
+        assert!(region(0, 16).overlaps(&region(4, 4)));
+        assert!(region(4, 4).overlaps(&region(0, 16)));
+        assert!(region(4, 4).overlaps(&region(4, 4)));
+    }
+
+    #[test]
+    fn partially_overlapping_regions_overlap() {
+        assert!(region(0, 8).overlaps(&region(4, 8)));
+        assert!(region(4, 8).overlaps(&region(0, 8)));
+    }
+
+    #[test]
+    fn parse_tier_pages_subtracts_free_pages() {
+        let stats = "fast total: 1024\tfree: 256\n\
+                     slow total: 4096\tfree: 4000\n\
+                     migrations: 12\n";
+        assert_eq!(parse_tier_pages(stats), Some((768, 96)));
+    }
+
+    #[test]
+    fn parse_tier_pages_without_tiers() {
+        assert_eq!(parse_tier_pages("promotions: 3\ndemotions: 4\n"), None);
+        assert_eq!(parse_tier_pages("fast total: 1024 free: 256\n"), None);
+    }
+
+    #[test]
+    fn tier_occupancy_lines_skip_missing_samples() {
+        let raw = "== 100\n\
+                   == 101\n\
+                   fast total=10 free=4\n\
+                   slow total=20 free=20\n\
+                   == 102\n\
+                   fast total=10 free=2\n\
+                   slow total=20 free=15\n";
+        assert_eq!(tier_occupancy_lines(raw), vec!["101 6 0", "102 8 5"]);
+    }
+}
@@ -1,6 +1,11 @@
+mod batch;
+mod check_kernel;
 mod fbmm_exp;
+mod metrics;
+mod retry;
 mod setup_kernel;
 mod setup_wkspc;
+mod summarize;
 
 const RESULTS_PATH: &str = "results/";
 const RESEARCH_WORKSPACE_PATH: &str = "research-workspace/";
@@ -8,6 +13,7 @@ const BMKS_PATH: &str = "bmks/";
 const SCRIPTS_PATH: &str = "scripts/";
 const SPEC2017_PATH: &str = "spec2017/";
 const PARSEC_PATH: &str = "parsec-3.0/";
+const GAP_PATH: &str = "gapbs/";
 const KERNEL_PATH: &str = "kernel/";
 
 fn run() -> Result<(), failure::Error> {
@@ -20,6 +26,10 @@ fn run() -> Result<(), failure::Error> {
         .subcommand(crate::setup_wkspc::cli_options())
         .subcommand(crate::setup_kernel::cli_options())
         .subcommand(crate::fbmm_exp::cli_options())
+        .subcommand(crate::summarize::cli_options())
+        .subcommand(crate::check_kernel::cli_options())
+        .subcommand(crate::retry::cli_options())
+        .subcommand(crate::batch::cli_options())
         .setting(clap::AppSettings::SubcommandRequiredElseHelp)
         .setting(clap::AppSettings::DisableVersion)
         .get_matches();
@@ -28,6 +38,10 @@ fn run() -> Result<(), failure::Error> {
         ("setup_wkspc", Some(sub_m)) => crate::setup_wkspc::run(sub_m),
         ("setup_kernel", Some(sub_m)) => crate::setup_kernel::run(sub_m),
         ("fbmm_exp", Some(sub_m)) => crate::fbmm_exp::run(sub_m),
+        ("summarize", Some(sub_m)) => crate::summarize::run(sub_m),
+        ("check_kernel", Some(sub_m)) => crate::check_kernel::run(sub_m),
+        ("retry", Some(sub_m)) => crate::retry::run(sub_m),
+        ("batch", Some(sub_m)) => crate::batch::run(sub_m),
         _ => {
             unreachable!();
         }
@@ -41,6 +55,14 @@ fn main() {
 
     std::env::set_var("RUST_BACKTRACE", "1");
 
+    // Don't leave the experiment running on the remote if we are interrupted
+    ctrlc::set_handler(|| {
+        println!("Interrupted.");
+        crate::fbmm_exp::teardown();
+        std::process::exit(130);
+    })
+    .expect("Unable to set the Ctrl-C handler");
+
     // If an error returned, try to print something helpful
     if let Err(err) = run() {
         const MESSAGE: &str = r#"== ERROR ==================================================================================
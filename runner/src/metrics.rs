@@ -0,0 +1,122 @@
+/// A tiny HTTP server on the driver that exposes the progress of the current run in the
+/// Prometheus text format so that long campaigns can be watched from a dashboard.
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+
+/// How long a scrape may take to send its request or read the response before it is dropped,
+/// so a stuck client can't keep the runner from exiting.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct MetricsState {
+    phase: &'static str,
+    start: Instant,
+    runtime_ms: Option<u128>,
+}
+
+pub struct MetricsServer {
+    port: u16,
+    state: Arc<Mutex<MetricsState>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Start serving `/metrics` on the given port of the driver machine.
+    pub fn start(port: u16) -> Result<Self, failure::Error> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let state = Arc::new(Mutex::new(MetricsState {
+            phase: "setup",
+            start: Instant::now(),
+            runtime_ms: None,
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        // A failed scrape shouldn't affect the experiment
+                        let _ = serve(stream, &state);
+                    }
+                }
+            })
+        };
+
+        Ok(MetricsServer {
+            port,
+            state,
+            shutdown,
+            handle: Some(handle),
+        })
+    }
+
+    /// Set the phase of the run that is currently in progress.
+    pub fn set_phase(&self, phase: &'static str) {
+        self.state.lock().unwrap().phase = phase;
+    }
+
+    /// Set the measured runtime of the workload.
+    pub fn set_runtime_ms(&self, runtime_ms: u128) {
+        self.state.lock().unwrap().runtime_ms = Some(runtime_ms);
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the server thread so it sees the shutdown
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(mut stream: TcpStream, state: &Mutex<MetricsState>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(SCRAPE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SCRAPE_TIMEOUT))?;
+
+    // We only serve one thing, so the contents of the request don't matter
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf)?;
+
+    let body = {
+        let state = state.lock().unwrap();
+        let mut body = format!(
+            "# TYPE runner_phase gauge\n\
+             runner_phase{{phase=\"{}\"}} 1\n\
+             # TYPE runner_elapsed_seconds gauge\n\
+             runner_elapsed_seconds {}\n",
+            state.phase,
+            state.start.elapsed().as_secs()
+        );
+        if let Some(runtime_ms) = state.runtime_ms {
+            body.push_str(&format!(
+                "# TYPE runner_workload_runtime_ms gauge\n\
+                 runner_workload_runtime_ms {}\n",
+                runtime_ms
+            ));
+        }
+        body
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
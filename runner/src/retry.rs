@@ -0,0 +1,19 @@
+/// Re-run an experiment exactly as it was configured, from the params file it saved.
+use clap::clap_app;
+
+pub fn cli_options() -> clap::App<'static, 'static> {
+    clap_app! { retry =>
+        (about: "Re-run a `fbmm_exp` experiment from its saved params file. Requires `sudo`.")
+        (@setting ArgRequiredElseHelp)
+        (@setting DisableVersion)
+        (@arg PARAMS_FILE: +required +takes_value
+         "The params file of the run to retry, copied to this machine.")
+    }
+}
+
+pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
+    let params_file = sub_m.value_of("PARAMS_FILE").unwrap();
+    let params = std::fs::read_to_string(params_file)?;
+
+    crate::fbmm_exp::retry(&params)
+}
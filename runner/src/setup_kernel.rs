@@ -1,7 +1,7 @@
 use clap::clap_app;
 
 use libscail::{
-    dir, get_git_hash, get_user_home_dir, GitRepo, KernelBaseConfigSource, KernelConfig,
+    dir, get_git_hash, get_user_home_dir, validator, GitRepo, KernelBaseConfigSource, KernelConfig,
     KernelPkgType, KernelSrc, Login,
 };
 
@@ -34,6 +34,11 @@ pub fn cli_options() -> clap::App<'static, 'static> {
          "(Optional) Install the perf corresponding to this kernel")
         (@arg BUILD_MMFS: --build_mmfs
          "(Optional) Build the in tree MMFS modules")
+        (@arg BUILD_JOBS: --build_jobs +takes_value {validator::is::<usize>}
+         "(Optional) The number of parallel jobs to build perf and the MMFS modules with. \
+         Default: the number of cores. This does NOT limit the kernel build: libscail's \
+         build_kernel takes no job count and picks its own, so the kernel build can still run \
+         out of memory on a small machine.")
     }
 }
 
@@ -50,6 +55,9 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
     let secret = sub_m.value_of("SECRET");
     let install_perf = sub_m.is_present("INSTALL_PERF");
     let build_mmfs = sub_m.is_present("BUILD_MMFS");
+    let build_jobs = sub_m
+        .value_of("BUILD_JOBS")
+        .map(|jobs| jobs.parse::<usize>().unwrap());
 
     let git_repo = if let Some(_secret) = &secret {
         GitRepo::HttpsPrivate {
@@ -70,6 +78,15 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
         .unwrap_or_else(|| vec![]);
 
     let ushell = SshShell::with_any_key(&login.username, &login.host)?;
+    let build_jobs = if let Some(jobs) = build_jobs {
+        println!(
+            "WARNING: --build_jobs only applies to perf and the MMFS modules. \
+             libscail still picks the kernel build's parallelism."
+        );
+        jobs
+    } else {
+        libscail::get_num_cores(&ushell)?
+    };
 
     let user_home = get_user_home_dir(&ushell)?;
     let kernel_path = dir!(&user_home, crate::KERNEL_PATH);
@@ -116,17 +133,25 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
     ushell.run(cmd!("sudo dpkg -i {} {}", kernel_deb, kernel_headers_deb).cwd(&kernel_path))?;
     ushell.run(cmd!("sudo grub-set-default 0"))?;
 
+    // Record which kernel we installed so runs can check that it is the one that booted. The
+    // image package is named after the release that `uname -r` reports.
+    let kernel_release = ushell
+        .run(cmd!("dpkg-deb -f {} Package", kernel_deb).cwd(&kernel_path))?
+        .stdout;
+    let kernel_release = kernel_release.trim().trim_start_matches("linux-image-");
+    libscail::set_remote_research_setting(&ushell, "kernel-release", kernel_release)?;
+
     if build_mmfs {
         let mmfs_dirs = ["BasicMMFS/", "TieredMMFS/", "ContigMMFS/", "BandwidthMMFS/"];
         for mmfs in mmfs_dirs {
             let mmfs_path = dir!(&kernel_path, mmfs);
-            ushell.run(cmd!("make").cwd(mmfs_path))?;
+            ushell.run(cmd!("make -j {}", build_jobs).cwd(mmfs_path))?;
         }
     }
 
     if install_perf {
         // Build perf
-        ushell.run(cmd!("make").cwd(&perf_path))?;
+        ushell.run(cmd!("make -j {}", build_jobs).cwd(&perf_path))?;
 
         // Put the new perf in place
         ushell.run(cmd!("sudo rm -f /usr/bin/perf"))?;
@@ -2,9 +2,10 @@
 /// all necessary software
 use clap::clap_app;
 
-use libscail::{clone_git_repo, dir, install_spec_2017, with_shell, GitRepo, Login};
+use libscail::{clone_git_repo, dir, install_spec_2017, validator, with_shell, GitRepo, Login};
 
 use spurs::{cmd, Execute, SshShell};
+use spurs_util::escape_for_bash;
 
 pub fn cli_options() -> clap::App<'static, 'static> {
     clap_app! { setup_wkspc =>
@@ -34,6 +35,10 @@ pub fn cli_options() -> clap::App<'static, 'static> {
           (e.g. /dev/sda might be /dev/sdb after a reboot). In this case, the device \
           names used in other arguments will be converted to stable names based on device ids.")
 
+        (@arg PMEM_NAMESPACES: +takes_value --pmem_namespaces ...
+         "(Optional) Put the given pmem namespaces (e.g. namespace0.0) in fsdax mode with a 2MB \
+         alignment for FBMM. Namespaces that are already configured that way are left alone.")
+
         (@arg CLONE_WKSPC: --clone_wkspc
          "(Optional) If passed, clone the workspace on the remote (or update if already cloned). \
          If the method uses HTTPS to access a private repository, the --secret option must also \
@@ -55,12 +60,20 @@ pub fn cli_options() -> clap::App<'static, 'static> {
 
         (@arg HOST_BMKS: --host_bmks
          "(Optional) If passed, build host benchmarks. This also makes them available to the guest.")
+        (@arg BMK_CC: --bmk_cc +takes_value requires[HOST_BMKS]
+         "(Optional) The compiler to build the microbenchmarks with (e.g. clang).")
+        (@arg BMK_CFLAGS: --bmk_cflags +takes_value requires[HOST_BMKS] +allow_hyphen_values
+         "(Optional) The CFLAGS to build the microbenchmarks with (e.g. \"-O3 -march=native\"). \
+         The flags used are recorded in bmks/build_flags on the remote.")
         (@arg SPEC_2017: --spec_2017 +takes_value
          "(Optional) If passed, setup and build SPEC 2017 on the remote machine (on the host only). \
           Because SPEC 2017 is not free, you need to pass runner a path to the SPEC 2017 ISO on the \
           driver machine. The ISO will be copied to the remote machine, mounted, and installed there.")
         (@arg JEMALLOC: --jemalloc
          "(Optional) set jemalloc as the system allocator.")
+        (@arg BUILD_JOBS: --build_jobs +takes_value {validator::is::<usize>}
+         "(Optional) The number of parallel jobs to build with. Lower this on machines with \
+         little memory. Default: the number of cores")
     }
 }
 
@@ -80,6 +93,8 @@ where
     swap_devices: Option<Vec<&'a str>>,
     /// Device names are unstable and should be converted to UUIDs.
     unstable_names: bool,
+    /// The pmem namespaces to put in fsdax mode.
+    pmem_namespaces: Option<Vec<&'a str>>,
 
     /// Should we clone/update the workspace?
     clone_wkspc: bool,
@@ -92,6 +107,10 @@ where
 
     /// Should we build host benchmarks?
     host_bmks: bool,
+    /// The compiler to build the microbenchmarks with.
+    bmk_cc: Option<&'a str>,
+    /// The CFLAGS to build the microbenchmarks with.
+    bmk_cflags: Option<&'a str>,
     /// Should we install SPEC 2017? If so, what is the ISO path?
     spec_2017: Option<&'a str>,
     /// Should we install HMSDK utilities
@@ -99,6 +118,9 @@ where
 
     /// Set jemalloc as the default system allocator.
     jemalloc: bool,
+
+    /// The number of parallel jobs to build with.
+    build_jobs: Option<usize>,
 }
 
 pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
@@ -113,6 +135,7 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
     let resize_root = sub_m.is_present("RESIZE_ROOT");
     let swap_devices = sub_m.values_of("SWAP_DEVS").map(|i| i.collect());
     let unstable_names = sub_m.is_present("UNSTABLE_DEVICE_NAMES");
+    let pmem_namespaces = sub_m.values_of("PMEM_NAMESPACES").map(|i| i.collect());
 
     let clone_wkspc = sub_m.is_present("CLONE_WKSPC");
     let git_user = sub_m.value_of("GIT_USER");
@@ -120,25 +143,35 @@ pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
     let secret = sub_m.value_of("SECRET");
 
     let host_bmks = sub_m.is_present("HOST_BMKS");
+    let bmk_cc = sub_m.value_of("BMK_CC");
+    let bmk_cflags = sub_m.value_of("BMK_CFLAGS");
     let spec_2017 = sub_m.value_of("SPEC_2017");
     let hmsdk = sub_m.is_present("HMSDK");
 
     let jemalloc = sub_m.is_present("JEMALLOC");
 
+    let build_jobs = sub_m
+        .value_of("BUILD_JOBS")
+        .map(|jobs| jobs.parse::<usize>().unwrap());
+
     let cfg = SetupConfig {
         login,
         host_dep,
         resize_root,
         swap_devices,
         unstable_names,
+        pmem_namespaces,
         clone_wkspc,
         git_user,
         wkspc_branch,
         secret,
         host_bmks,
+        bmk_cc,
+        bmk_cflags,
         spec_2017,
         hmsdk,
         jemalloc,
+        build_jobs,
     };
 
     run_inner(cfg)?;
@@ -152,6 +185,11 @@ where
 {
     // Connect to the remote
     let ushell = SshShell::with_any_key(cfg.login.username, &cfg.login.host)?;
+    let build_jobs = if let Some(jobs) = cfg.build_jobs {
+        jobs
+    } else {
+        libscail::get_num_cores(&ushell)?
+    };
 
     if cfg.host_dep {
         install_host_dependencies(&ushell)?;
@@ -169,7 +207,7 @@ where
     }
 
     if cfg.host_bmks {
-        build_host_benchmarks(&ushell)?;
+        build_host_benchmarks(&ushell, cfg.bmk_cc, cfg.bmk_cflags, build_jobs)?;
     }
 
     if let Some(iso_path) = cfg.spec_2017 {
@@ -199,7 +237,7 @@ where
         with_shell! { ushell in &numactl_dir =>
             cmd!("./autogen.sh"),
             cmd!("./configure"),
-            cmd!("make"),
+            cmd!("make -j {}", build_jobs),
         }
     }
 
@@ -220,12 +258,18 @@ fn install_host_dependencies(ushell: &SshShell) -> Result<(), failure::Error> {
             "libdw-dev",
             "libncurses-dev",
             "libevent-dev",
+            "libgflags-dev",
+            "libsnappy-dev",
+            "liblz4-dev",
+            "libzstd-dev",
             "dwarves",
             "libpci-dev",
             "numactl",
             "linux-tools-common",
             "openjdk-8-jdk",
             "fuse",
+            "stress-ng",
+            "xfsprogs",
             "redis-server",
             "python2",
             "python3",
@@ -296,14 +340,37 @@ where
     Ok(())
 }
 
-fn build_host_benchmarks(ushell: &SshShell) -> Result<(), failure::Error> {
-    let num_cores = libscail::get_num_cores(ushell)?;
-
+fn build_host_benchmarks(
+    ushell: &SshShell,
+    bmk_cc: Option<&str>,
+    bmk_cflags: Option<&str>,
+    build_jobs: usize,
+) -> Result<(), failure::Error> {
     ushell.run(cmd!("mkdir -p {}", crate::RESULTS_PATH))?;
 
     // Build microbenchmarks
     let bmks_dir = dir!(crate::RESEARCH_WORKSPACE_PATH, crate::BMKS_PATH);
-    ushell.run(cmd!("make").cwd(bmks_dir))?;
+    let mut make_vars = String::new();
+    if let Some(cc) = bmk_cc {
+        make_vars.push_str(&format!(" CC={}", escape_for_bash(cc)));
+    }
+    if let Some(cflags) = bmk_cflags {
+        make_vars.push_str(&format!(" CFLAGS={}", escape_for_bash(cflags)));
+    }
+    // Always rebuild, so nothing built with other flags is left behind and build_flags is
+    // true for every binary
+    ushell.run(cmd!("make clean").cwd(&bmks_dir))?;
+    ushell.run(cmd!("make -j {}{}", build_jobs, make_vars).cwd(&bmks_dir))?;
+    // Record how the binaries were built so results can refer to it
+    ushell.run(cmd!(
+        "echo {} > {}",
+        escape_for_bash(&format!(
+            "CC={} CFLAGS={}",
+            bmk_cc.unwrap_or("default"),
+            bmk_cflags.unwrap_or("default")
+        )),
+        dir!(&bmks_dir, "build_flags")
+    ))?;
 
     // Download PARSEC and build canneal
     let parsec_repo = GitRepo::HttpsPublic {
@@ -312,11 +379,53 @@ fn build_host_benchmarks(ushell: &SshShell) -> Result<(), failure::Error> {
     clone_git_repo(ushell, parsec_repo, Some("parsec-3.0"), None, None, &[])?;
     ushell.run(cmd!("./parsecmgmt -a build -p canneal").cwd("parsec-3.0/bin/"))?;
 
+    // Download and build the GAP benchmark suite
+    let gap_repo = GitRepo::HttpsPublic {
+        repo: "github.com/sbeamer/gapbs.git",
+    };
+    clone_git_repo(ushell, gap_repo, Some(crate::GAP_PATH), None, None, &[])?;
+    ushell.run(cmd!("make -j {}", build_jobs).cwd(crate::GAP_PATH))?;
+
+    // multichase, for pointer chasing latency
+    let multichase_repo = GitRepo::HttpsPublic {
+        repo: "github.com/google/multichase.git",
+    };
+    let multichase_dir = dir!(
+        crate::RESEARCH_WORKSPACE_PATH,
+        crate::BMKS_PATH,
+        "multichase"
+    );
+    clone_git_repo(
+        ushell,
+        multichase_repo,
+        Some(&multichase_dir),
+        None,
+        None,
+        &[],
+    )?;
+    ushell.run(cmd!("make -j {}", build_jobs).cwd(&multichase_dir))?;
+
+    // XSBench, using the OpenMP version
+    let xsbench_repo = GitRepo::HttpsPublic {
+        repo: "github.com/ANL-CESAR/XSBench.git",
+    };
+    let xsbench_dir = dir!(crate::RESEARCH_WORKSPACE_PATH, crate::BMKS_PATH, "XSBench");
+    clone_git_repo(ushell, xsbench_repo, Some(&xsbench_dir), None, None, &[])?;
+    ushell.run(cmd!("make -j {}", build_jobs).cwd(dir!(&xsbench_dir, "openmp-threading")))?;
+
+    // RocksDB, for db_bench
+    let rocksdb_repo = GitRepo::HttpsPublic {
+        repo: "github.com/facebook/rocksdb.git",
+    };
+    let rocksdb_dir = dir!(crate::RESEARCH_WORKSPACE_PATH, crate::BMKS_PATH, "rocksdb");
+    clone_git_repo(ushell, rocksdb_repo, Some(&rocksdb_dir), None, None, &[])?;
+    ushell.run(cmd!("make -j {} DEBUG_LEVEL=0 db_bench", build_jobs).cwd(&rocksdb_dir))?;
+
     // memcached
     with_shell! { ushell in &dir!(crate::RESEARCH_WORKSPACE_PATH, crate::BMKS_PATH, "memcached") =>
         cmd!("./autogen.sh"),
         cmd!("./configure"),
-        cmd!("make -j {}", num_cores),
+        cmd!("make -j {}", build_jobs),
     }
 
     // Build YCSB
@@ -338,7 +447,7 @@ fn build_host_benchmarks(ushell: &SshShell) -> Result<(), failure::Error> {
     let postgres_dir = dir!(crate::RESEARCH_WORKSPACE_PATH, crate::BMKS_PATH, "postgres");
     with_shell! { ushell in &postgres_dir =>
         cmd!("./configure"),
-        cmd!("make"),
+        cmd!("make -j {}", build_jobs),
         cmd!("sudo make install"),
     }
 
@@ -386,5 +495,42 @@ where
         }
     }
 
+    if let Some(namespaces) = &cfg.pmem_namespaces {
+        set_up_pmem_namespaces(ushell, namespaces)?;
+    }
+
+    Ok(())
+}
+
+/// Put the given pmem namespaces in fsdax mode with a 2MB alignment, unless they already are.
+fn set_up_pmem_namespaces(ushell: &SshShell, namespaces: &[&str]) -> Result<(), failure::Error> {
+    const ALIGN: u64 = 2 << 20;
+
+    with_shell! { ushell =>
+        spurs_util::ubuntu::apt_install(&["ndctl", "daxctl"]),
+    };
+
+    let current = crate::fbmm_exp::list_pmem_namespaces(ushell)?;
+
+    for name in namespaces {
+        let namespace = current
+            .iter()
+            .find(|ns| ns["dev"].as_str() == Some(*name))
+            .ok_or_else(|| failure::format_err!("No pmem namespace named {}", name))?;
+
+        if namespace["mode"].as_str() == Some("fsdax") && namespace["align"].as_u64() == Some(ALIGN)
+        {
+            println!("{} is already in fsdax mode with a 2MB alignment", name);
+            continue;
+        }
+
+        ushell.run(cmd!(
+            "sudo ndctl create-namespace --force -e {} --mode=fsdax --align=2M",
+            name
+        ))?;
+    }
+
+    ushell.run(cmd!("sudo ndctl list -N"))?;
+
     Ok(())
 }
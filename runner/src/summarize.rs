@@ -0,0 +1,243 @@
+/// Turn the results of a completed run into a single CSV row so that post-processing
+/// lives next to the code that produces the result files.
+use clap::clap_app;
+
+pub fn cli_options() -> clap::App<'static, 'static> {
+    clap_app! { summarize =>
+        (about: "Print the params and scalar results of a completed run as a CSV row, or a \
+         table of all of the runs in a results directory.")
+        (@setting ArgRequiredElseHelp)
+        (@setting DisableVersion)
+        (@arg RESULTS: +required +takes_value
+         "The results path printed by the experiment (the `RESULTS:` line), with the results \
+         copied to this machine. If this is a directory, every run under it is summarized.")
+        (@arg NO_HEADER: --no_header
+         "Do not print the CSV header. Useful when appending rows from many runs.")
+        (@arg FORMAT: --format +takes_value possible_values(&["csv", "json"])
+         "The output format. Default: csv")
+    }
+}
+
+pub fn run(sub_m: &clap::ArgMatches<'_>) -> Result<(), failure::Error> {
+    let results = sub_m.value_of("RESULTS").unwrap();
+    let no_header = sub_m.is_present("NO_HEADER");
+    let json = sub_m.value_of("FORMAT") == Some("json");
+
+    let (header, rows) = if std::path::Path::new(results).is_dir() {
+        summarize_dir(results)?
+    } else {
+        let (header, row) = summarize(results)?;
+        (header, vec![row])
+    };
+
+    if json {
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                header
+                    .iter()
+                    .cloned()
+                    .zip(row.into_iter().map(serde_json::Value::String))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        if !no_header {
+            println!("{}", header.join(","));
+        }
+        for row in rows {
+            let row = row
+                .iter()
+                .map(|value| csv_escape(value))
+                .collect::<Vec<_>>();
+            println!("{}", row.join(","));
+        }
+    }
+
+    Ok(())
+}
+
+/// The scalar metrics in every summary, in column order. A metric that a run did not produce
+/// is left empty so rows from different runs line up.
+const METRICS: &[&str] = &[
+    "runtime_ms",
+    "ycsb_throughput",
+    "gups",
+    "gups_pre_move",
+    "gups_post_move",
+    "fbmm_file_create_times",
+    "fbmm_file_register_times",
+    "fbmm_munmap_timeap_times",
+];
+
+/// Summarize every run with a params file under `dir`, one row per run. Runs without a
+/// runtime file, or whose params can't be read, are marked incomplete.
+fn summarize_dir(dir: &str) -> Result<(Vec<String>, Vec<Vec<String>>), failure::Error> {
+    let mut params_files = Vec::new();
+    let mut dirs = vec![std::path::PathBuf::from(dir)];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.to_string_lossy().ends_with("params") {
+                params_files.push(path);
+            }
+        }
+    }
+    params_files.sort();
+
+    let mut columns = None;
+    let mut summaries = Vec::new();
+    for params_file in params_files {
+        let results = params_file
+            .to_string_lossy()
+            .trim_end_matches("params")
+            .to_owned();
+        let summary = summarize(&results).ok();
+        if let (None, Some((header, _))) = (&columns, &summary) {
+            columns = Some(header.clone());
+        }
+        summaries.push((results, summary.map(|(_, row)| row)));
+    }
+
+    // Every run has the same columns, so any run that could be read gives the header
+    let columns =
+        columns.unwrap_or_else(|| METRICS.iter().map(|metric| metric.to_string()).collect());
+    let runtime_col = columns.iter().position(|col| col == "runtime_ms");
+    let rows = summaries
+        .into_iter()
+        .map(|(results, summary)| {
+            let complete = match (&summary, runtime_col) {
+                (Some(row), Some(col)) => !row[col].is_empty(),
+                _ => false,
+            };
+            let mut row = vec![results];
+            row.extend(summary.unwrap_or_else(|| vec![String::new(); columns.len()]));
+            row.push(if complete { "complete" } else { "incomplete" }.into());
+            row
+        })
+        .collect();
+
+    let mut header = vec!["results".to_owned()];
+    header.extend(columns);
+    header.push("status".into());
+
+    Ok((header, rows))
+}
+
+/// Read the results files with the given prefix and produce the header and values of the CSV.
+/// The columns are the same for every run: those from the params file, then `METRICS`.
+fn summarize(results: &str) -> Result<(Vec<String>, Vec<String>), failure::Error> {
+    let params = std::fs::read_to_string(format!("{}params", results))?;
+    let mut columns = crate::fbmm_exp::summary_columns(&params)?;
+
+    let mut metrics = std::collections::HashMap::new();
+    if let Ok(runtime) = std::fs::read_to_string(format!("{}runtime", results)) {
+        metrics.insert("runtime_ms".to_owned(), runtime.trim().to_owned());
+    }
+
+    if let Ok(ycsb) = std::fs::read_to_string(format!("{}ycsb", results)) {
+        // YCSB reports a line like "[OVERALL], Throughput(ops/sec), 1234.5"
+        if let Some(throughput) = ycsb
+            .lines()
+            .find(|line| line.starts_with("[OVERALL], Throughput"))
+            .and_then(|line| line.rsplit(',').next())
+        {
+            metrics.insert("ycsb_throughput".into(), throughput.trim().into());
+        }
+    }
+
+    if let Ok(gups_metric) = std::fs::read_to_string(format!("{}gups_metric", results)) {
+        metrics.extend(parse_counters(&gups_metric));
+    }
+
+    // Only what changed while the workload ran, so counters left over from earlier runs on the
+    // same boot don't count
+    if let (Ok(before), Ok(after)) = (
+        std::fs::read_to_string(format!("{}fbmm_stats_before", results)),
+        std::fs::read_to_string(format!("{}fbmm_stats", results)),
+    ) {
+        metrics.extend(
+            fbmm_stats_deltas(&before, &after)
+                .into_iter()
+                .map(|(key, delta)| (format!("fbmm_{}", key), delta)),
+        );
+    }
+
+    columns.extend(METRICS.iter().map(|metric| {
+        (
+            metric.to_string(),
+            metrics.remove(*metric).unwrap_or_default(),
+        )
+    }));
+
+    Ok(columns.into_iter().unzip())
+}
+
+/// How much each FBMM counter grew between two snapshots of the FBMM stats. Each counter is on
+/// a line like "file create times: <value> ...", and the first value after the colon is used.
+fn fbmm_stats_deltas(before: &str, after: &str) -> Vec<(String, String)> {
+    let parse = |stats: &str| {
+        stats
+            .lines()
+            .filter_map(|line| {
+                let (key, values) = line.split_once(':')?;
+                let value = values.split_whitespace().next()?.parse::<f64>().ok()?;
+                Some((key.trim().replace(' ', "_"), value))
+            })
+            .collect::<Vec<_>>()
+    };
+    let before = parse(before);
+
+    parse(after)
+        .into_iter()
+        .filter_map(|(key, after)| {
+            let (_, before) = before.iter().find(|(k, _)| *k == key)?;
+            Some((key, (after - before).to_string()))
+        })
+        .collect()
+}
+
+/// Parse lines of the form "<name> <value>" or "<name>: <value>" where the value is numeric.
+fn parse_counters(stats: &str) -> Vec<(String, String)> {
+    stats
+        .lines()
+        .filter_map(|line| {
+            let mut split = line.rsplitn(2, |c: char| c.is_whitespace() || c == ':');
+            let value = split.next()?.trim();
+            let key = split.next()?.trim().trim_end_matches(':').trim();
+            if key.is_empty() || value.parse::<f64>().is_err() {
+                return None;
+            }
+            Some((key.replace(' ', "_"), value.into()))
+        })
+        .collect()
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fbmm_stats_deltas_subtract_the_snapshot() {
+        let before = "file create times: 10 200\nfile register times: 3 40\n";
+        let after = "file create times: 15 300\nfile register times: 3 40\nfailed: 2\n";
+        assert_eq!(
+            fbmm_stats_deltas(before, after),
+            vec![
+                ("file_create_times".to_owned(), "5".to_owned()),
+                ("file_register_times".to_owned(), "0".to_owned()),
+            ]
+        );
+    }
+}